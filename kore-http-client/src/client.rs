@@ -0,0 +1,686 @@
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt, stream};
+use reqwest::{Method, RequestBuilder};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    error::ClientError,
+    query::{
+        DiffQuery, EventFirstLastQuery, EventSnQuery, EventsCursorQuery, EventsQuery, ExportQuery,
+        GovQuery, RoleQuery, SignaturesQuery, StateQuery, SubjectQuery,
+    },
+    retry::RetryPolicy,
+    wrappers::{
+        ApproveInfo, BundleReport, CacheMetrics, Config, CursorEvents, EventInfo, EventsPage,
+        ExportFormat, GovsData, Member, Policy, RegisterDataSubj, RequestData, RequestInfo, Role,
+        Schema, SignaturesInfo, SignaturesQuorum, StateDiff, StateResponse, SubjectInfo,
+        SubjectState, TransferSubject,
+    },
+};
+
+/// Prefix of the version of the API used by the client.
+const API_PREFIX: &str = "/v1";
+
+/// Number of subjects requested per page by [`KoreClient::subjects_stream`].
+const SUBJECTS_PAGE: u64 = 100;
+
+/// Typed client for the HTTP API of a Kore node.
+#[derive(Debug, Clone)]
+pub struct KoreClient {
+    http: reqwest::Client,
+    base_url: String,
+    retry: RetryPolicy,
+}
+
+impl KoreClient {
+    /// Creates a client for the node listening at `base_url`, such as `http://localhost:3000`.
+    pub fn new(base_url: &str) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Creates a client that sends its requests through the given `reqwest` client, to set
+    /// timeouts, default headers or TLS settings.
+    pub fn with_http_client(base_url: &str, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets how requests are retried after transient failures.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{}{}", self.base_url, API_PREFIX, path))
+    }
+
+    async fn execute(&self, request: RequestBuilder) -> Result<Bytes, ClientError> {
+        let request = request.build()?;
+        let retries = if matches!(
+            *request.method(),
+            Method::GET | Method::PUT | Method::DELETE
+        ) {
+            self.retry.max_retries
+        } else {
+            0
+        };
+
+        let mut retry = 0;
+        loop {
+            let Some(attempt) = request.try_clone() else {
+                return self.attempt(request).await;
+            };
+
+            match self.attempt(attempt).await {
+                Err(e) if retry < retries && e.is_transient() => {
+                    retry += 1;
+                    tokio::time::sleep(self.retry.backoff(retry)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn attempt(&self, request: reqwest::Request) -> Result<Bytes, ClientError> {
+        let response = self.http.execute(request).await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        if status.is_success() {
+            Ok(body)
+        } else {
+            Err(ClientError::from_response(status, &body))
+        }
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        let body = self.execute(request).await?;
+        serde_json::from_slice(&body).map_err(|e| ClientError::Decode(e.to_string()))
+    }
+
+    async fn empty(&self, request: RequestBuilder) -> Result<(), ClientError> {
+        self.execute(request).await.map(|_| ())
+    }
+
+    /// Sends a request once and streams the body of its response, without buffering it.
+    async fn stream(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<impl Stream<Item = Result<Bytes, ClientError>> + use<>, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.bytes().await?;
+            return Err(ClientError::from_response(status, &body));
+        }
+
+        Ok(response.bytes_stream().map_err(ClientError::from))
+    }
+
+    /// Sends a signed event request, encoded as accepted by `POST /event-request`.
+    pub async fn send_event_request<T: Serialize + ?Sized>(
+        &self,
+        request: &T,
+    ) -> Result<RequestData, ClientError> {
+        self.json(self.request(Method::POST, "/event-request").json(request))
+            .await
+    }
+
+    pub async fn get_request_state(&self, request_id: &str) -> Result<RequestInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/event-request/{}", request_id)))
+            .await
+    }
+
+    pub async fn get_approval(&self, subject_id: &str) -> Result<ApproveInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/approval-request/{}", subject_id)))
+            .await
+    }
+
+    /// Accepts or rejects the pending approval request of a subject.
+    pub async fn patch_approval(
+        &self,
+        subject_id: &str,
+        response: &str,
+    ) -> Result<String, ClientError> {
+        self.json(
+            self.request(Method::PATCH, &format!("/approval-request/{}", subject_id))
+                .json(response),
+        )
+        .await
+    }
+
+    pub async fn put_auth(
+        &self,
+        subject_id: &str,
+        witnesses: &[String],
+    ) -> Result<String, ClientError> {
+        self.json(
+            self.request(Method::PUT, &format!("/auth/{}", subject_id))
+                .json(witnesses),
+        )
+        .await
+    }
+
+    pub async fn get_all_auth_subjects(&self) -> Result<Vec<String>, ClientError> {
+        self.json(self.request(Method::GET, "/auth")).await
+    }
+
+    pub async fn get_witnesses_subject(
+        &self,
+        subject_id: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        self.json(self.request(Method::GET, &format!("/auth/{}", subject_id)))
+            .await
+    }
+
+    pub async fn delete_auth_subject(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(Method::DELETE, &format!("/auth/{}", subject_id)))
+            .await
+    }
+
+    pub async fn update_subject(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(Method::POST, &format!("/update/{}", subject_id)))
+            .await
+    }
+
+    pub async fn check_transfer(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(Method::POST, &format!("/check-transfer/{}", subject_id)))
+            .await
+    }
+
+    pub async fn manual_distribution(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(
+            Method::POST,
+            &format!("/manual-distribution/{}", subject_id),
+        ))
+        .await
+    }
+
+    pub async fn get_all_govs(&self, query: &GovQuery) -> Result<Vec<GovsData>, ClientError> {
+        self.json(
+            self.request(Method::GET, "/register-governances")
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_all_subjects(
+        &self,
+        governance_id: &str,
+        query: &SubjectQuery,
+    ) -> Result<Vec<RegisterDataSubj>, ClientError> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/register-subjects/{}", governance_id),
+            )
+            .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_governance_members(
+        &self,
+        governance_id: &str,
+    ) -> Result<Vec<Member>, ClientError> {
+        self.json(self.request(
+            Method::GET,
+            &format!("/governances/{}/members", governance_id),
+        ))
+        .await
+    }
+
+    pub async fn get_governance_roles(
+        &self,
+        governance_id: &str,
+        query: &RoleQuery,
+    ) -> Result<Vec<Role>, ClientError> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/governances/{}/roles", governance_id),
+            )
+            .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_governance_schemas(
+        &self,
+        governance_id: &str,
+    ) -> Result<Vec<Schema>, ClientError> {
+        self.json(self.request(
+            Method::GET,
+            &format!("/governances/{}/schemas", governance_id),
+        ))
+        .await
+    }
+
+    pub async fn get_governance_policies(
+        &self,
+        governance_id: &str,
+    ) -> Result<Vec<Policy>, ClientError> {
+        self.json(self.request(
+            Method::GET,
+            &format!("/governances/{}/policies", governance_id),
+        ))
+        .await
+    }
+
+    /// A numbered page of events or, if any cursor parameter is given, a page keyed by sn.
+    pub async fn get_events(
+        &self,
+        subject_id: &str,
+        query: &EventsQuery,
+    ) -> Result<EventsPage, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/events/{}", subject_id))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_event_sn(&self, subject_id: &str, sn: u64) -> Result<EventInfo, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/event/{}", subject_id))
+                .query(&EventSnQuery { sn }),
+        )
+        .await
+    }
+
+    pub async fn get_events_cursor(
+        &self,
+        subject_id: &str,
+        query: &EventsCursorQuery,
+    ) -> Result<CursorEvents, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/subjects/{}/events", subject_id))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_first_or_end_events(
+        &self,
+        subject_id: &str,
+        query: &EventFirstLastQuery,
+    ) -> Result<Vec<EventInfo>, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/events-first-last/{}", subject_id))
+                .query(query),
+        )
+        .await
+    }
+
+    /// Current state of a subject, optionally projected with `fields` or a JSONPath `query`.
+    pub async fn get_state(
+        &self,
+        subject_id: &str,
+        fields: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<SubjectInfo, ClientError> {
+        let query = StateQuery {
+            sn: None,
+            fields: fields.map(str::to_owned),
+            query: query.map(str::to_owned),
+        };
+        self.json(
+            self.request(Method::GET, &format!("/state/{}", subject_id))
+                .query(&query),
+        )
+        .await
+    }
+
+    /// State of a subject right after the event with the given sn.
+    pub async fn get_state_at(
+        &self,
+        subject_id: &str,
+        sn: u64,
+        fields: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<SubjectState, ClientError> {
+        let query = StateQuery {
+            sn: Some(sn),
+            fields: fields.map(str::to_owned),
+            query: query.map(str::to_owned),
+        };
+        self.json(
+            self.request(Method::GET, &format!("/state/{}", subject_id))
+                .query(&query),
+        )
+        .await
+    }
+
+    pub async fn get_state_diff(
+        &self,
+        subject_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<StateDiff, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/state/{}/diff", subject_id))
+                .query(&DiffQuery { from, to }),
+        )
+        .await
+    }
+
+    pub async fn get_signatures(&self, subject_id: &str) -> Result<SignaturesInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/signatures/{}", subject_id)))
+            .await
+    }
+
+    pub async fn get_signatures_quorum(
+        &self,
+        subject_id: &str,
+        sn: Option<u64>,
+    ) -> Result<SignaturesQuorum, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/signatures/{}/quorum", subject_id))
+                .query(&SignaturesQuery { sn }),
+        )
+        .await
+    }
+
+    /// Zip bundle with the complete history of a subject.
+    pub async fn export_subject(&self, subject_id: &str) -> Result<Bytes, ClientError> {
+        self.execute(self.request(Method::GET, &format!("/subjects/{}/export", subject_id)))
+            .await
+    }
+
+    pub async fn verify_bundle(&self, bundle: Bytes) -> Result<BundleReport, ClientError> {
+        self.json(self.request(Method::POST, "/verify/bundle").body(bundle))
+            .await
+    }
+
+    pub async fn get_controller_id(&self) -> Result<String, ClientError> {
+        self.json(self.request(Method::GET, "/controller-id")).await
+    }
+
+    pub async fn get_peer_id(&self) -> Result<String, ClientError> {
+        self.json(self.request(Method::GET, "/peer-id")).await
+    }
+
+    pub async fn get_config(&self) -> Result<Config, ClientError> {
+        self.json(self.request(Method::GET, "/config")).await
+    }
+
+    /// Zip file with the private key of the node.
+    pub async fn get_keys(&self) -> Result<Bytes, ClientError> {
+        self.execute(self.request(Method::GET, "/keys")).await
+    }
+
+    pub async fn get_pending_transfers(&self) -> Result<Vec<TransferSubject>, ClientError> {
+        self.json(self.request(Method::GET, "/pending-transfers"))
+            .await
+    }
+
+    /// Current state of a subject or, with `sn`, its state right after that event, optionally
+    /// projected with `fields` or a JSONPath `query`.
+    pub async fn get_subject(
+        &self,
+        subject_id: &str,
+        query: &StateQuery,
+    ) -> Result<StateResponse, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/subjects/{}", subject_id))
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_subject_diff(
+        &self,
+        subject_id: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<StateDiff, ClientError> {
+        self.json(
+            self.request(Method::GET, &format!("/subjects/{}/diff", subject_id))
+                .query(&DiffQuery { from, to }),
+        )
+        .await
+    }
+
+    pub async fn get_subject_event(
+        &self,
+        subject_id: &str,
+        sn: u64,
+    ) -> Result<EventInfo, ClientError> {
+        self.json(self.request(
+            Method::GET,
+            &format!("/subjects/{}/events/{}", subject_id, sn),
+        ))
+        .await
+    }
+
+    pub async fn get_subject_signatures(
+        &self,
+        subject_id: &str,
+    ) -> Result<SignaturesInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/subjects/{}/signatures", subject_id)))
+            .await
+    }
+
+    pub async fn get_subject_signatures_quorum(
+        &self,
+        subject_id: &str,
+        sn: Option<u64>,
+    ) -> Result<SignaturesQuorum, ClientError> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/subjects/{}/signatures/quorum", subject_id),
+            )
+            .query(&SignaturesQuery { sn }),
+        )
+        .await
+    }
+
+    pub async fn get_subject_witnesses(
+        &self,
+        subject_id: &str,
+    ) -> Result<Vec<String>, ClientError> {
+        self.json(self.request(Method::GET, &format!("/subjects/{}/witnesses", subject_id)))
+            .await
+    }
+
+    pub async fn put_subject_witnesses(
+        &self,
+        subject_id: &str,
+        witnesses: &[String],
+    ) -> Result<(), ClientError> {
+        self.empty(
+            self.request(Method::PUT, &format!("/subjects/{}/witnesses", subject_id))
+                .json(witnesses),
+        )
+        .await
+    }
+
+    pub async fn delete_subject_witnesses(&self, subject_id: &str) -> Result<(), ClientError> {
+        self.empty(self.request(
+            Method::DELETE,
+            &format!("/subjects/{}/witnesses", subject_id),
+        ))
+        .await
+    }
+
+    pub async fn get_subject_approval(&self, subject_id: &str) -> Result<ApproveInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/subjects/{}/approval", subject_id)))
+            .await
+    }
+
+    /// Accepts or rejects the pending approval request of a subject.
+    pub async fn patch_subject_approval(
+        &self,
+        subject_id: &str,
+        response: &str,
+    ) -> Result<String, ClientError> {
+        self.json(
+            self.request(Method::PATCH, &format!("/subjects/{}/approval", subject_id))
+                .json(response),
+        )
+        .await
+    }
+
+    pub async fn post_subject_update(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(Method::POST, &format!("/subjects/{}/updates", subject_id)))
+            .await
+    }
+
+    pub async fn post_subject_distribution(&self, subject_id: &str) -> Result<String, ClientError> {
+        self.json(self.request(
+            Method::POST,
+            &format!("/subjects/{}/distributions", subject_id),
+        ))
+        .await
+    }
+
+    pub async fn post_subject_transfer_check(
+        &self,
+        subject_id: &str,
+    ) -> Result<String, ClientError> {
+        self.json(self.request(
+            Method::POST,
+            &format!("/subjects/{}/transfer-checks", subject_id),
+        ))
+        .await
+    }
+
+    pub async fn get_governances(&self, query: &GovQuery) -> Result<Vec<GovsData>, ClientError> {
+        self.json(self.request(Method::GET, "/governances").query(query))
+            .await
+    }
+
+    pub async fn get_governance_subjects(
+        &self,
+        governance_id: &str,
+        query: &SubjectQuery,
+    ) -> Result<Vec<RegisterDataSubj>, ClientError> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/governances/{}/subjects", governance_id),
+            )
+            .query(query),
+        )
+        .await
+    }
+
+    /// Sends a signed event request, encoded as accepted by `POST /requests`.
+    pub async fn post_request<T: Serialize + ?Sized>(
+        &self,
+        request: &T,
+    ) -> Result<RequestData, ClientError> {
+        self.json(self.request(Method::POST, "/requests").json(request))
+            .await
+    }
+
+    pub async fn get_request(&self, request_id: &str) -> Result<RequestInfo, ClientError> {
+        self.json(self.request(Method::GET, &format!("/requests/{}", request_id)))
+            .await
+    }
+
+    pub async fn get_transfers(&self) -> Result<Vec<TransferSubject>, ClientError> {
+        self.json(self.request(Method::GET, "/transfers")).await
+    }
+
+    pub async fn get_cache_metrics(&self) -> Result<CacheMetrics, ClientError> {
+        self.json(self.request(Method::GET, "/cache-metrics")).await
+    }
+
+    /// Whether the node takes new requests, which fails with a 503 once it is shutting down.
+    pub async fn get_readiness(&self) -> Result<String, ClientError> {
+        self.json(self.request(Method::GET, "/health/ready")).await
+    }
+
+    /// Streams every event of a subject as NDJSON or CSV, as the node reads them.
+    pub async fn export_subject_events(
+        &self,
+        subject_id: &str,
+        format: ExportFormat,
+    ) -> Result<impl Stream<Item = Result<Bytes, ClientError>> + use<>, ClientError> {
+        self.stream(
+            self.request(Method::GET, &format!("/events/{}/export", subject_id))
+                .query(&ExportQuery {
+                    format: Some(format),
+                }),
+        )
+        .await
+    }
+
+    /// Streams every event of a governance and of its subjects as NDJSON or CSV, as the node
+    /// reads them.
+    pub async fn export_governance_events(
+        &self,
+        governance_id: &str,
+        format: ExportFormat,
+    ) -> Result<impl Stream<Item = Result<Bytes, ClientError>> + use<>, ClientError> {
+        self.stream(
+            self.request(
+                Method::GET,
+                &format!("/governances/{}/events/export", governance_id),
+            )
+            .query(&ExportQuery {
+                format: Some(format),
+            }),
+        )
+        .await
+    }
+
+    /// Streams the events of a subject, following the cursors of `/subjects/{id}/events` from the
+    /// position given by `query` until the last page.
+    pub fn events_stream<'a>(
+        &'a self,
+        subject_id: &'a str,
+        query: EventsCursorQuery,
+    ) -> impl Stream<Item = Result<EventInfo, ClientError>> + 'a {
+        stream::try_unfold(Some(query), move |query| async move {
+            let Some(query) = query else {
+                return Ok::<_, ClientError>(None);
+            };
+
+            let page = self.get_events_cursor(subject_id, &query).await?;
+            let next = page.next.map(|cursor| EventsCursorQuery {
+                after_sn: None,
+                before_sn: None,
+                cursor: Some(cursor),
+                ..query
+            });
+
+            Ok(Some((stream::iter(page.events.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+
+    /// Streams every subject of a governance matching `query`, requesting one page at a time.
+    /// The `quantity` and `page` of the query are ignored.
+    pub fn subjects_stream<'a>(
+        &'a self,
+        governance_id: &'a str,
+        query: SubjectQuery,
+    ) -> impl Stream<Item = Result<RegisterDataSubj, ClientError>> + 'a {
+        stream::try_unfold(Some(1), move |page| {
+            let query = SubjectQuery {
+                quantity: Some(SUBJECTS_PAGE),
+                page,
+                ..query.clone()
+            };
+            async move {
+                let Some(page) = query.page else {
+                    return Ok::<_, ClientError>(None);
+                };
+
+                let subjects = self.get_all_subjects(governance_id, &query).await?;
+                let next = (subjects.len() as u64 == SUBJECTS_PAGE).then_some(page + 1);
+
+                Ok(Some((stream::iter(subjects.into_iter().map(Ok)), next)))
+            }
+        })
+        .try_flatten()
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+/// Error returned by [`KoreClient`](crate::KoreClient).
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent or its response could not be read.
+    Http(reqwest::Error),
+    /// The response could not be decoded into the expected type.
+    Decode(String),
+    /// The node answered with an error status.
+    Api { status: StatusCode, message: String },
+}
+
+impl ClientError {
+    /// Status returned by the node, if the request reached it.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Http(error) => error.status(),
+            ClientError::Decode(_) => None,
+            ClientError::Api { status, .. } => Some(*status),
+        }
+    }
+
+    /// Whether the same request may succeed if sent again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::Http(error) => error.is_connect() || error.is_timeout(),
+            ClientError::Api { status, .. } => matches!(
+                *status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            ClientError::Decode(_) => false,
+        }
+    }
+
+    /// Builds the error of a response with an error status from its body.
+    pub(crate) fn from_response(status: StatusCode, body: &[u8]) -> Self {
+        let message = serde_json::from_slice::<String>(body)
+            .unwrap_or_else(|_| String::from_utf8_lossy(body).into_owned());
+        ClientError::Api { status, message }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(error) => write!(f, "HTTP error: {}", error),
+            ClientError::Decode(error) => write!(f, "Invalid response: {}", error),
+            ClientError::Api { status, message } => write!(f, "{}: {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(value: reqwest::Error) -> Self {
+        ClientError::Http(value)
+    }
+}
@@ -0,0 +1,18 @@
+//! Typed async client for the HTTP API of a Kore node served by kore-http.
+//!
+//! Requests and responses have their own types, in [`wrappers`] and [`query`], which match
+//! those of the server over the wire.
+
+mod client;
+mod error;
+pub mod query;
+mod retry;
+pub mod wrappers;
+
+pub use client::KoreClient;
+pub use error::ClientError;
+pub use query::{
+    DiffQuery, EventFirstLastQuery, EventSnQuery, EventsCursorQuery, EventsQuery, ExportQuery,
+    GovQuery, RoleQuery, SignaturesQuery, StateQuery, SubjectQuery,
+};
+pub use retry::RetryPolicy;
@@ -0,0 +1,90 @@
+//! Query parameters of the routes of the API.
+
+use serde::Serialize;
+
+use crate::wrappers::{EventType, ExportFormat, RoleType, SortField};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SubjectQuery {
+    pub active: Option<bool>,
+    pub schema: Option<String>,
+    pub namespace: Option<String>,
+    pub search: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: Option<bool>,
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GovQuery {
+    pub active: Option<bool>,
+    pub search: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: Option<bool>,
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoleQuery {
+    pub role: Option<RoleType>,
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventsQuery {
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
+    pub after_sn: Option<u64>,
+    pub before_sn: Option<u64>,
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+    pub event_type: Option<EventType>,
+    pub success: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventsCursorQuery {
+    pub after_sn: Option<u64>,
+    pub before_sn: Option<u64>,
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+    pub event_type: Option<EventType>,
+    pub success: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StateQuery {
+    pub sn: Option<u64>,
+    pub fields: Option<String>,
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffQuery {
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SignaturesQuery {
+    pub sn: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventSnQuery {
+    pub sn: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventFirstLastQuery {
+    pub quantity: Option<u64>,
+    pub reverse: Option<bool>,
+    pub success: Option<bool>,
+}
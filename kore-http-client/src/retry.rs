@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+/// How requests are retried after transient failures.
+///
+/// Only idempotent requests (`GET`, `PUT` and `DELETE`) are retried, so an event
+/// request is never sent twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled on each following one.
+    pub initial_backoff: Duration,
+    /// Upper bound of the wait between retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Wait before the given retry, starting at 1.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
@@ -0,0 +1,608 @@
+//! Requests and responses of the API as sent over the wire, so that the client does not depend
+//! on the server or the node.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferSubject {
+    pub subject_id: String,
+    pub new_owner: String,
+    pub actual_owner: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatorEvents {
+    pub paginator: Paginator,
+    pub events: Vec<EventInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventInfo {
+    pub subject_id: String,
+    pub sn: u64,
+    pub patch: Option<Value>,
+    pub error: Option<ProtocolsError>,
+    pub event_req: EventRequestInfo,
+    pub succes: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Paginator {
+    pub pages: u64,
+    pub next: Option<u64>,
+    pub prev: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolsError {
+    pub evaluation: Option<String>,
+    pub validation: Option<String>,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EventRequestInfo {
+    Create(CreateRequestInfo),
+    Fact(FactRequestInfo),
+    Transfer(TransferRequestInfo),
+    Confirm(ConfirmRequestInfo),
+    Reject(RejectRequestInfo),
+    EOL(EOLRequestInfo),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRequestInfo {
+    pub governance_id: String,
+    pub schema_id: String,
+    pub namespace: Namespace,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRequestInfo {
+    pub subject_id: String,
+    pub new_owner: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmRequestInfo {
+    pub subject_id: String,
+    pub name_old_owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectRequestInfo {
+    pub subject_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EOLRequestInfo {
+    pub subject_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactRequestInfo {
+    pub subject_id: String,
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Namespace(Vec<String>);
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
+impl From<&str> for Namespace {
+    fn from(str: &str) -> Self {
+        let tokens: Vec<String> = str
+            .split('.')
+            .filter(|x| !x.trim().is_empty())
+            .map(|s| s.to_string())
+            .collect();
+
+        Namespace(tokens)
+    }
+}
+
+impl From<String> for Namespace {
+    fn from(str: String) -> Self {
+        Namespace::from(str.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestData {
+    pub request_id: String,
+    pub subject_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GovsData {
+    pub governance_id: String,
+    pub active: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterDataSubj {
+    pub subject_id: String,
+    pub schema: String,
+    pub active: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestInfo {
+    pub status: String,
+    pub version: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApproveInfo {
+    pub state: String,
+    pub request: ApprovalReqInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalReqInfo {
+    /// The signed event request.
+    pub event_request: SignedInfo<FactInfo>,
+    /// The sequence number of the event.
+    pub sn: u64,
+    /// The version of the governance contract.
+    pub gov_version: u64,
+    /// The patch to apply to the state.
+    pub patch: Value,
+    /// The hash of the state after applying the patch.
+    pub state_hash: String,
+    /// The hash of the previous event.
+    pub hash_prev_event: String,
+    /// The hash of the previous event.
+    pub subject_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedInfo<T>
+where
+    T: Serialize + Clone,
+{
+    pub content: T,
+    pub signature: SignatureInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactInfo {
+    pub payload: Value,
+    pub subject_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SignatureInfo {
+    pub signer: String,
+    pub timestamp: u64,
+    pub content_hash: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubjectInfo {
+    pub subject_id: String,
+    pub governance_id: String,
+    pub genesis_gov_version: u64,
+    pub namespace: String,
+    pub schema_id: String,
+    pub owner: String,
+    pub creator: String,
+    pub active: bool,
+    pub sn: u64,
+    pub properties: Value,
+    pub new_owner: Option<String>,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignaturesInfo {
+    pub subject_id: String,
+    pub sn: u64,
+    pub signatures_eval: Option<HashSet<ProtocolsSignaturesInfo>>,
+    pub signatures_appr: Option<HashSet<ProtocolsSignaturesInfo>>,
+    pub signatures_vali: HashSet<ProtocolsSignaturesInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ProtocolsSignaturesInfo {
+    Signature(SignatureInfo),
+    TimeOut(TimeOutResponseInfo),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct TimeOutResponseInfo {
+    pub who: String,
+    pub re_trys: u32,
+    pub timestamp: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub kore_config: KoreConfig,
+    pub keys_path: String,
+    pub prometheus: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KoreConfig {
+    pub key_derivator: String,
+    pub digest_derivator: String,
+    pub kore_db: String,
+    pub external_db: String,
+    pub network: NetworkConfig,
+    pub contracts_dir: String,
+    pub always_accept: bool,
+    pub garbage_collector: u64,
+    pub sink: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    pub user_agent: String,
+    pub node_type: String,
+    pub listen_addresses: Vec<String>,
+    pub external_addresses: Vec<String>,
+    pub tell: TellConfig,
+    pub routing: RoutingConfig,
+    pub port_reuse: bool,
+    pub control_list: ControlListConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ControlListConfig {
+    pub enable: bool,
+    pub allow_list: Vec<String>,
+    pub block_list: Vec<String>,
+    pub service_allow_list: Vec<String>,
+    pub service_block_list: Vec<String>,
+    pub interval_request: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TellConfig {
+    pub message_timeout: u64,
+    pub max_concurrent_streams: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoutingConfig {
+    pub boot_nodes: Vec<RoutingNode>,
+    pub dht_random_walk: bool,
+    pub pre_routing: bool,
+    pub discovery_only_if_under_num: u64,
+    pub allow_non_globals_in_dht: bool,
+    pub allow_private_ip: bool,
+    pub enable_mdns: bool,
+    pub kademlia_disjoint_query_paths: bool,
+    pub kademlia_replication_factor: Option<usize>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoutingNode {
+    pub peer_id: String,
+    pub address: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Member {
+    /// The controller id of the member.
+    pub id: String,
+    /// The name of the member, unique within the governance.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Role {
+    /// Who holds the role.
+    pub who: Who,
+    /// The namespace in which the role applies, empty for all of them.
+    pub namespace: String,
+    /// The role itself.
+    pub role: RoleType,
+    /// The schemas to which the role applies.
+    pub schema: SchemaEnum,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoleType {
+    Approver,
+    Evaluator,
+    Validator,
+    Witness,
+    Creator,
+    Issuer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Who {
+    /// A member given its controller id.
+    Id(String),
+    /// A member given its name.
+    Name(String),
+    /// Every member of the governance.
+    Members,
+    /// Anyone, member or not.
+    All,
+    /// Anyone who is not a member of the governance.
+    NotMembers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SchemaEnum {
+    /// A single schema given its id.
+    Id(String),
+    /// Every schema except the governance one.
+    NotGovernance,
+    /// Every schema.
+    All,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schema {
+    /// The identifier of the schema.
+    pub id: String,
+    /// The JSON Schema that the state of the subjects must satisfy, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    /// The initial state of the subjects of this schema.
+    pub initial_value: Value,
+    /// The contract that evaluates the facts of the subjects.
+    pub contract: Contract,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contract {
+    /// The source code of the contract encoded in base64.
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Policy {
+    /// The identifier of the schema the policy applies to.
+    pub id: String,
+    /// The quorum required in the evaluation phase.
+    pub evaluate: Validation,
+    /// The quorum required in the approval phase.
+    pub approve: Validation,
+    /// The quorum required in the validation phase.
+    pub validate: Validation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Validation {
+    pub quorum: Quorum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Quorum {
+    /// More than half of the signers.
+    Majority,
+    /// A fixed number of signers.
+    Fixed(u32),
+    /// A percentage of the signers, between 0 and 1.
+    Percentage(f64),
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EventType {
+    Create,
+    Fact,
+    Transfer,
+    Confirm,
+    Reject,
+    EOL,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorEvents {
+    /// The events of the page, in ascending order of sn.
+    pub events: Vec<EventInfo>,
+    /// Cursor to the next page, if any.
+    pub next: Option<String>,
+    /// Cursor to the previous page, if any.
+    pub prev: Option<String>,
+}
+
+/// A page of events, numbered or keyed by sn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EventsPage {
+    Numbered(PaginatorEvents),
+    Cursor(CursorEvents),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    Name,
+    Schema,
+    Sn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubjectState {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The identifier of the governance of the subject.
+    pub governance_id: String,
+    /// The schema of the subject.
+    pub schema_id: String,
+    /// The sequence number at which the state was rebuilt.
+    pub sn: u64,
+    /// The state of the subject at that sequence number.
+    pub properties: Value,
+    /// The Blake3 hash of the canonical JSON serialization of the properties. It is not the
+    /// state hash of the node.
+    pub properties_hash: String,
+}
+
+/// The current state of a subject, or its state rebuilt at a given sn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StateResponse {
+    Current(SubjectInfo),
+    Past(SubjectState),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDiff {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The sequence number of the initial state.
+    pub from: u64,
+    /// The sequence number of the final state.
+    pub to: u64,
+    /// The RFC 6902 JSON Patch that turns the initial state into the final one.
+    pub patch: Value,
+    /// The changes of each field.
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// JSON pointer to the field.
+    pub path: String,
+    /// The value of the field in the initial state, if it existed.
+    pub from: Option<Value>,
+    /// The value of the field in the final state, if it exists.
+    pub to: Option<Value>,
+    /// The sequence numbers of the events that touched the field.
+    pub events: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleReport {
+    /// The identifier of the subject of the bundle, if the manifest could be read.
+    pub subject_id: Option<String>,
+    /// The last sequence number of the bundle, if the manifest could be read.
+    pub sn: Option<u64>,
+    /// The consistency checks that failed. No errors does not prove that the bundle is authentic.
+    pub errors: Vec<BundleError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleError {
+    /// The sequence number of the event that failed, if the error concerns one.
+    pub sn: Option<u64>,
+    /// The file of the bundle that failed, if the error concerns one.
+    pub file: Option<String>,
+    /// Why the check failed.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignaturesQuorum {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The sequence number of the event.
+    pub sn: u64,
+    /// The version of the governance in force when the event was signed.
+    pub governance_version: u64,
+    /// The analysis of the evaluation signatures, if the event was evaluated.
+    pub evaluation: Option<ProtocolQuorum>,
+    /// The analysis of the approval signatures, if the event was approved.
+    pub approval: Option<ProtocolQuorum>,
+    /// The analysis of the validation signatures.
+    pub validation: ProtocolQuorum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolQuorum {
+    /// The quorum required by the policy of the schema.
+    pub quorum: Quorum,
+    /// Whether the role is open to any controller or to those that are not members, so that
+    /// `eligible` only lists the members that hold it by name or id.
+    pub open: bool,
+    /// The controller ids of the members that hold the role, if they can be enumerated.
+    pub eligible: Vec<String>,
+    /// The number of eligible signatures required to reach the quorum.
+    pub required: u64,
+    /// Whether the quorum was reached.
+    pub reached: bool,
+    /// The signers of the event.
+    pub signers: Vec<Signer>,
+    /// The members that did not answer in time.
+    pub timed_out: Vec<TimedOutSigner>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signer {
+    /// The controller id of the signer.
+    pub signer: String,
+    /// The name of the signer in the governance, if it is a member.
+    pub name: Option<String>,
+    /// Whether the signer holds the role required by the protocol.
+    pub eligible: bool,
+    /// The timestamp of the signature.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimedOutSigner {
+    /// The controller id of the member.
+    pub who: String,
+    /// The name of the member in the governance, if any.
+    pub name: Option<String>,
+    /// The number of retries before giving up.
+    pub re_trys: u32,
+    /// The timestamp of the timeout.
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMetrics {
+    /// Whether responses are cached, which requires a memory limit.
+    pub enabled: bool,
+    /// The number of cached responses.
+    pub entries: u64,
+    /// The approximate memory used by the cached responses, in bytes.
+    pub memory: u64,
+    /// The memory over which the least recently used responses are evicted, in bytes.
+    pub max_memory: u64,
+    /// The metrics of each cached route.
+    pub routes: Vec<RouteCacheMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteCacheMetrics {
+    /// The name of the route: governances, subjects or state.
+    pub route: String,
+    /// The time to live of the responses of the route, in milliseconds.
+    pub ttl: u64,
+    /// The number of requests answered from the cache.
+    pub hits: u64,
+    /// The number of requests that had to ask the node.
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    #[default]
+    Ndjson,
+    /// One row per event, with the request flattened into columns.
+    Csv,
+}
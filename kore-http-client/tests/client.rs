@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use futures_util::{StreamExt, TryStreamExt};
+use kore_http_client::{
+    ClientError, EventsCursorQuery, GovQuery, KoreClient, RetryPolicy, wrappers::ExportFormat,
+};
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, query_param},
+};
+
+/// Starts a mock node and a client for it that does not retry.
+async fn start() -> (MockServer, KoreClient) {
+    let server = MockServer::start().await;
+    let client = KoreClient::new(&server.uri()).with_retry(RetryPolicy::none());
+    (server, client)
+}
+
+fn event(sn: u64) -> serde_json::Value {
+    json!({
+        "subject_id": "Jsubject",
+        "sn": sn,
+        "patch": [],
+        "error": null,
+        "event_req": { "Fact": { "subject_id": "Jsubject", "payload": {} } },
+        "succes": true
+    })
+}
+
+#[tokio::test]
+async fn decodes_responses_of_the_versioned_routes() {
+    let (server, client) = start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/controller-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json("Econtroller"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/register-governances"))
+        .and(query_param("active", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "governance_id": "Jgovernance", "active": true, "name": null, "description": null }
+        ])))
+        .mount(&server)
+        .await;
+
+    assert_eq!(client.get_controller_id().await.unwrap(), "Econtroller");
+
+    let query = GovQuery {
+        active: Some(true),
+        ..GovQuery::default()
+    };
+    let governances = client.get_all_govs(&query).await.unwrap();
+    assert_eq!(governances.len(), 1);
+    assert_eq!(governances[0].governance_id, "Jgovernance");
+}
+
+#[tokio::test]
+async fn maps_error_responses() {
+    let (server, client) = start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/governances/Jmissing/members"))
+        .respond_with(ResponseTemplate::new(500).set_body_json("Governance not found"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/requests"))
+        .respond_with(ResponseTemplate::new(400).set_body_json("Subject Jsubject is not active"))
+        .mount(&server)
+        .await;
+
+    let error = client.get_governance_members("Jmissing").await.unwrap_err();
+    assert!(
+        matches!(&error, ClientError::Api { message, .. } if message == "Governance not found")
+    );
+    assert!(!error.is_transient());
+
+    let error = client.post_request(&json!({})).await.unwrap_err();
+    assert_eq!(error.status().map(|x| x.as_u16()), Some(400));
+    assert!(
+        matches!(&error, ClientError::Api { message, .. } if message == "Subject Jsubject is not active")
+    );
+}
+
+#[tokio::test]
+async fn retries_idempotent_requests_after_transient_failures() {
+    let server = MockServer::start().await;
+    let client = KoreClient::new(&server.uri()).with_retry(RetryPolicy {
+        max_retries: 1,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(1),
+    });
+    Mock::given(method("GET"))
+        .and(path("/v1/peer-id"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/peer-id"))
+        .respond_with(ResponseTemplate::new(200).set_body_json("12D3KooW"))
+        .mount(&server)
+        .await;
+
+    assert_eq!(client.get_peer_id().await.unwrap(), "12D3KooW");
+}
+
+#[tokio::test]
+async fn streams_events_following_cursors() {
+    let (server, client) = start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/subjects/Jsubject/events"))
+        .and(query_param("cursor", "next"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "events": [event(2)],
+            "next": null,
+            "prev": "prev"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/subjects/Jsubject/events"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "events": [event(0), event(1)],
+            "next": "next",
+            "prev": null
+        })))
+        .mount(&server)
+        .await;
+
+    let events: Vec<u64> = client
+        .events_stream("Jsubject", EventsCursorQuery::default())
+        .map_ok(|event| event.sn)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(events, vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn streams_exports() {
+    let (server, client) = start().await;
+    let ndjson = format!("{}\n{}\n", event(0), event(1));
+    Mock::given(method("GET"))
+        .and(path("/v1/events/Jsubject/export"))
+        .and(query_param("format", "ndjson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ndjson.clone()))
+        .mount(&server)
+        .await;
+
+    let chunks: Vec<bytes::Bytes> = client
+        .export_subject_events("Jsubject", ExportFormat::Ndjson)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), ndjson.as_bytes());
+
+    let error = client
+        .export_governance_events("Jmissing", ExportFormat::Csv)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(error.status().map(|x| x.as_u16()), Some(404));
+}
+
+#[tokio::test]
+async fn verifies_bundles() {
+    let (server, client) = start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/verify/bundle"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "subject_id": null,
+            "sn": null,
+            "errors": [{ "sn": null, "file": null, "reason": "Invalid zip file" }]
+        })))
+        .mount(&server)
+        .await;
+
+    let report = client
+        .verify_bundle(bytes::Bytes::from_static(b"not a zip"))
+        .await
+        .unwrap();
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.subject_id.is_none());
+
+    let mut events = Box::pin(client.events_stream("Jsubject", EventsCursorQuery::default()));
+    assert!(matches!(
+        events.next().await,
+        Some(Err(ClientError::Api { .. }))
+    ));
+}
@@ -0,0 +1,543 @@
+use std::{io, process::ExitCode};
+
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use futures_util::TryStreamExt;
+use kore_http_client::{
+    ClientError, EventsCursorQuery, GovQuery, KoreClient, SubjectQuery,
+    wrappers::{EventType, SortField},
+};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use serde_json::json;
+
+use output::Output;
+use profile::{DEFAULT_ENDPOINT, Profiles};
+
+mod output;
+mod profile;
+
+/// Command-line client for the HTTP API of a Kore node.
+#[derive(Debug, Parser)]
+#[command(name = "kore-cli", version)]
+struct Cli {
+    /// Profile of the profile file to use, instead of the default one.
+    #[arg(long, short, global = true, env = "KORE_CLI_PROFILE")]
+    profile: Option<String>,
+    /// URL of the node, overriding the profile.
+    #[arg(long, global = true, env = "KORE_CLI_ENDPOINT")]
+    endpoint: Option<String>,
+    /// Bearer token, overriding the profile.
+    #[arg(long, global = true, env = "KORE_CLI_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Format of the output.
+    #[arg(long, short, global = true, value_enum, default_value_t = Output::Table)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// State of a subject, now or after a given event.
+    State {
+        subject_id: String,
+        /// Sequence number of the event after which to get the state.
+        #[arg(long)]
+        sn: Option<u64>,
+        /// Comma separated JSON pointers of the properties to keep.
+        #[arg(long)]
+        fields: Option<String>,
+        /// JSONPath query over the properties.
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// Events of a subject.
+    Events {
+        subject_id: String,
+        /// Only events with a sequence number greater than this one.
+        #[arg(long)]
+        after_sn: Option<u64>,
+        /// Maximum number of events of a page.
+        #[arg(long)]
+        limit: Option<u64>,
+        #[arg(long, value_parser = parse_event_type)]
+        event_type: Option<EventType>,
+        #[arg(long)]
+        success: Option<bool>,
+        /// Follow every page instead of printing only the first one.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Signatures of the last event of a subject.
+    Signatures {
+        subject_id: String,
+        /// Resolve the signers against the governance and check each quorum.
+        #[arg(long)]
+        quorum: bool,
+    },
+    /// Accepts or rejects the pending approval request of a subject.
+    Approve {
+        subject_id: String,
+        /// Reject the request instead of accepting it.
+        #[arg(long)]
+        reject: bool,
+    },
+    /// Witnesses authorized to receive copies of subjects.
+    #[command(subcommand)]
+    Auth(AuthCommand),
+    /// Subject transfers.
+    #[command(subcommand)]
+    Transfer(TransferCommand),
+    /// Governances to which the node belongs.
+    Govs {
+        #[arg(long)]
+        active: Option<bool>,
+        #[arg(long)]
+        search: Option<String>,
+        #[command(flatten)]
+        page: Page,
+    },
+    /// Subjects of a governance.
+    Subjects {
+        governance_id: String,
+        #[arg(long)]
+        active: Option<bool>,
+        #[arg(long)]
+        schema: Option<String>,
+        #[arg(long)]
+        namespace: Option<String>,
+        #[arg(long)]
+        search: Option<String>,
+        #[command(flatten)]
+        page: Page,
+    },
+    /// Profiles of the profile file, or the configuration of the node.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Prints the completions script for a shell.
+    Completions { shell: Shell },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuthCommand {
+    /// Authorizes a subject, with the witnesses that will send copies of it.
+    Add {
+        subject_id: String,
+        witnesses: Vec<String>,
+    },
+    /// Removes the authorization of a subject.
+    Rm { subject_id: String },
+    /// Lists the authorized subjects, or the witnesses of a subject.
+    Ls { subject_id: Option<String> },
+}
+
+#[derive(Debug, Subcommand)]
+enum TransferCommand {
+    /// Transfers waiting to be confirmed or rejected.
+    Pending,
+    /// Confirms the transfer of a subject to this node.
+    Confirm {
+        subject_id: String,
+        /// Name under which the previous owner is kept.
+        #[arg(long)]
+        name_old_owner: Option<String>,
+    },
+    /// Rejects the transfer of a subject to this node.
+    Reject { subject_id: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Configuration of the node.
+    Node,
+    /// Lists the profiles, with their tokens masked.
+    Ls,
+    /// Creates or updates a profile with the given `--endpoint` and `--token`.
+    Set {
+        name: String,
+        /// Make it the default profile.
+        #[arg(long)]
+        default: bool,
+    },
+    /// Removes a profile.
+    Rm { name: String },
+    /// Makes a profile the default one.
+    Use { name: String },
+}
+
+#[derive(Debug, Args)]
+struct Page {
+    #[arg(long, value_parser = parse_sort_field)]
+    sort: Option<SortField>,
+    #[arg(long)]
+    reverse: bool,
+    /// Number of items of a page. Every item is printed if not set.
+    #[arg(long)]
+    quantity: Option<u64>,
+    /// Page to print, starting at 1.
+    #[arg(long)]
+    page: Option<u64>,
+}
+
+fn parse_event_type(value: &str) -> Result<EventType, String> {
+    serde_json::from_value(json!(value))
+        .map_err(|_| "expected one of Create, Fact, Transfer, Confirm, Reject, EOL".to_owned())
+}
+
+fn parse_sort_field(value: &str) -> Result<SortField, String> {
+    serde_json::from_value(json!(value)).map_err(|_| "expected one of name, schema, sn".to_owned())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    if let Command::Completions { shell } = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "kore-cli", &mut io::stdout());
+        return Ok(());
+    }
+
+    if let Command::Config(command) = &cli.command
+        && !matches!(command, ConfigCommand::Node)
+    {
+        return config(&cli, command);
+    }
+
+    let client = client(&cli)?;
+    let output = cli.output;
+    let error = |e: ClientError| e.to_string();
+
+    match cli.command {
+        Command::State {
+            subject_id,
+            sn,
+            fields,
+            query,
+        } => match sn {
+            Some(sn) => output.print(
+                &client
+                    .get_state_at(&subject_id, sn, fields.as_deref(), query.as_deref())
+                    .await
+                    .map_err(error)?,
+            ),
+            None => output.print(
+                &client
+                    .get_state(&subject_id, fields.as_deref(), query.as_deref())
+                    .await
+                    .map_err(error)?,
+            ),
+        },
+        Command::Events {
+            subject_id,
+            after_sn,
+            limit,
+            event_type,
+            success,
+            all,
+        } => {
+            let query = EventsCursorQuery {
+                after_sn,
+                limit,
+                event_type,
+                success,
+                ..Default::default()
+            };
+
+            if all {
+                let events: Vec<_> = client
+                    .events_stream(&subject_id, query)
+                    .try_collect()
+                    .await
+                    .map_err(error)?;
+                output.print(&events)
+            } else {
+                let page = client
+                    .get_events_cursor(&subject_id, &query)
+                    .await
+                    .map_err(error)?;
+                output.print(&page.events)?;
+                if let Some(next) = page.next {
+                    eprintln!(
+                        "More events available, use --all or --after-sn to see them ({})",
+                        next
+                    );
+                }
+                Ok(())
+            }
+        }
+        Command::Signatures { subject_id, quorum } => {
+            if quorum {
+                output.print(
+                    &client
+                        .get_signatures_quorum(&subject_id, None)
+                        .await
+                        .map_err(error)?,
+                )
+            } else {
+                output.print(&client.get_signatures(&subject_id).await.map_err(error)?)
+            }
+        }
+        Command::Approve { subject_id, reject } => {
+            let response = if reject { "Rejected" } else { "Accepted" };
+            output.print(
+                &client
+                    .patch_approval(&subject_id, response)
+                    .await
+                    .map_err(error)?,
+            )
+        }
+        Command::Auth(AuthCommand::Add {
+            subject_id,
+            witnesses,
+        }) => output.print(
+            &client
+                .put_auth(&subject_id, &witnesses)
+                .await
+                .map_err(error)?,
+        ),
+        Command::Auth(AuthCommand::Rm { subject_id }) => output.print(
+            &client
+                .delete_auth_subject(&subject_id)
+                .await
+                .map_err(error)?,
+        ),
+        Command::Auth(AuthCommand::Ls { subject_id }) => match subject_id {
+            Some(subject_id) => output.print(
+                &client
+                    .get_witnesses_subject(&subject_id)
+                    .await
+                    .map_err(error)?,
+            ),
+            None => output.print(&client.get_all_auth_subjects().await.map_err(error)?),
+        },
+        Command::Transfer(TransferCommand::Pending) => {
+            output.print(&client.get_pending_transfers().await.map_err(error)?)
+        }
+        Command::Transfer(TransferCommand::Confirm {
+            subject_id,
+            name_old_owner,
+        }) => {
+            let request = json!({
+                "request": {
+                    "Confirm": {
+                        "subject_id": subject_id,
+                        "name_old_owner": name_old_owner,
+                    }
+                }
+            });
+            output.print(&client.send_event_request(&request).await.map_err(error)?)
+        }
+        Command::Transfer(TransferCommand::Reject { subject_id }) => {
+            let request = json!({
+                "request": {
+                    "Reject": {
+                        "subject_id": subject_id,
+                    }
+                }
+            });
+            output.print(&client.send_event_request(&request).await.map_err(error)?)
+        }
+        Command::Govs {
+            active,
+            search,
+            page,
+        } => {
+            let query = GovQuery {
+                active,
+                search,
+                sort: page.sort,
+                reverse: Some(page.reverse),
+                quantity: page.quantity,
+                page: page.page,
+            };
+            output.print(&client.get_all_govs(&query).await.map_err(error)?)
+        }
+        Command::Subjects {
+            governance_id,
+            active,
+            schema,
+            namespace,
+            search,
+            page,
+        } => {
+            let query = SubjectQuery {
+                active,
+                schema,
+                namespace,
+                search,
+                sort: page.sort,
+                reverse: Some(page.reverse),
+                quantity: page.quantity,
+                page: page.page,
+            };
+            output.print(
+                &client
+                    .get_all_subjects(&governance_id, &query)
+                    .await
+                    .map_err(error)?,
+            )
+        }
+        Command::Config(ConfigCommand::Node) => {
+            output.print(&client.get_config().await.map_err(error)?)
+        }
+        Command::Config(_) | Command::Completions { .. } => Ok(()),
+    }
+}
+
+/// Manages the profiles of the profile file.
+fn config(cli: &Cli, command: &ConfigCommand) -> Result<(), String> {
+    let path = profile::profiles_path()?;
+    let mut profiles = Profiles::read(&path)?;
+    let not_found = |name: &str| format!("Profile {} not found in {}", name, path.display());
+
+    match command {
+        ConfigCommand::Ls => {
+            let list: Vec<_> = profiles
+                .profiles
+                .iter()
+                .map(|(name, profile)| {
+                    json!({
+                        "name": name,
+                        "default": profiles.default.as_deref() == Some(name.as_str()),
+                        "endpoint": profile.endpoint,
+                        "token": profile.token.as_ref().map(|_| "********"),
+                    })
+                })
+                .collect();
+            return cli.output.print(&list);
+        }
+        ConfigCommand::Set { name, default } => {
+            let profile = profiles.profiles.entry(name.clone()).or_default();
+            if let Some(endpoint) = &cli.endpoint {
+                profile.endpoint = Some(endpoint.clone());
+            }
+            if let Some(token) = &cli.token {
+                profile.token = Some(token.clone());
+            }
+            if *default {
+                profiles.default = Some(name.clone());
+            }
+        }
+        ConfigCommand::Rm { name } => {
+            profiles
+                .profiles
+                .remove(name)
+                .ok_or_else(|| not_found(name))?;
+            if profiles.default.as_ref() == Some(name) {
+                profiles.default = None;
+            }
+        }
+        ConfigCommand::Use { name } => {
+            if !profiles.profiles.contains_key(name) {
+                return Err(not_found(name));
+            }
+            profiles.default = Some(name.clone());
+        }
+        ConfigCommand::Node => return Ok(()),
+    }
+
+    profiles.write(&path)
+}
+
+/// Builds the client from the flags, falling back to the selected profile.
+fn client(cli: &Cli) -> Result<KoreClient, String> {
+    let profile = profile::load(cli.profile.as_deref())?;
+
+    let endpoint = cli
+        .endpoint
+        .clone()
+        .or(profile.endpoint)
+        .unwrap_or(DEFAULT_ENDPOINT.to_owned());
+
+    let mut headers = HeaderMap::new();
+    if let Some(token) = cli.token.clone().or(profile.token) {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| "The token contains invalid characters".to_owned())?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    let http = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| format!("Error building HTTP client: {}", e))?;
+
+    Ok(KoreClient::with_http_client(&endpoint, http))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_global_flags_after_the_command() {
+        let cli = Cli::try_parse_from([
+            "kore-cli",
+            "events",
+            "J1",
+            "--after-sn",
+            "3",
+            "--event-type",
+            "Fact",
+            "-o",
+            "json",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.output, Output::Json);
+        let Command::Events {
+            subject_id,
+            after_sn,
+            event_type,
+            ..
+        } = cli.command
+        else {
+            panic!("expected the events command");
+        };
+        assert_eq!(subject_id, "J1");
+        assert_eq!(after_sn, Some(3));
+        assert!(matches!(event_type, Some(EventType::Fact)));
+    }
+
+    #[test]
+    fn config_set_takes_the_connection_flags() {
+        let cli = Cli::try_parse_from([
+            "kore-cli",
+            "config",
+            "set",
+            "production",
+            "--endpoint",
+            "https://kore.example.com",
+            "--default",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.endpoint.as_deref(), Some("https://kore.example.com"));
+        assert!(matches!(
+            cli.command,
+            Command::Config(ConfigCommand::Set { ref name, default: true }) if name == "production"
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(Cli::try_parse_from(["kore-cli", "events", "J1", "--event-type", "Nope"]).is_err());
+        assert!(Cli::try_parse_from(["kore-cli", "govs", "--sort", "nope"]).is_err());
+        assert!(Cli::try_parse_from(["kore-cli", "config"]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL_CONDENSED};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Format in which results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    Table,
+    Json,
+    Yaml,
+}
+
+impl Output {
+    /// Prints a result in the selected format.
+    pub fn print<T: Serialize>(&self, value: &T) -> Result<(), String> {
+        let value =
+            serde_json::to_value(value).map_err(|e| format!("Error encoding result: {}", e))?;
+
+        let text = match self {
+            Output::Table => table(&value),
+            Output::Json => serde_json::to_string_pretty(&value)
+                .map_err(|e| format!("Error encoding result: {}", e))?,
+            Output::Yaml => serde_yaml::to_string(&value)
+                .map_err(|e| format!("Error encoding result: {}", e))?,
+        };
+
+        println!("{}", text.trim_end());
+        Ok(())
+    }
+}
+
+/// Renders a list of objects with a column per field, an object with a row per field and
+/// anything else as plain text. Nested values are shown as compact JSON.
+fn table(value: &Value) -> String {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) && !items.is_empty() => {
+            let mut columns: Vec<&String> = vec![];
+            for item in items.iter().filter_map(Value::as_object) {
+                for key in item.keys() {
+                    if !columns.contains(&key) {
+                        columns.push(key);
+                    }
+                }
+            }
+
+            table.set_header(columns.iter().map(|x| x.as_str()));
+            for item in items {
+                table.add_row(columns.iter().map(|key| cell(&item[key.as_str()])));
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                table.add_row(vec![cell(item)]);
+            }
+        }
+        Value::Object(fields) => {
+            table.set_header(vec!["field", "value"]);
+            for (key, value) in fields {
+                table.add_row(vec![key.clone(), cell(value)]);
+            }
+        }
+        value => return cell(value),
+    }
+
+    table.to_string()
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn lists_of_objects_have_a_column_per_field() {
+        let text = table(&json!([
+            { "name": "a", "sn": 1 },
+            { "name": "b", "active": true },
+        ]));
+        let lines: Vec<_> = text.lines().collect();
+
+        assert!(lines[1].contains("name") && lines[1].contains("sn"));
+        assert!(lines[1].contains("active"));
+        assert!(text.contains(" a ") && text.contains(" b "));
+        assert_eq!(text.matches("true").count(), 1);
+    }
+
+    #[test]
+    fn objects_have_a_row_per_field() {
+        let text = table(&json!({ "subject_id": "J1", "properties": { "a": 1 } }));
+
+        assert!(text.contains("field") && text.contains("value"));
+        assert!(text.contains("J1"));
+        assert!(text.contains(r#"{"a":1}"#));
+    }
+
+    #[test]
+    fn scalars_are_plain_text() {
+        assert_eq!(table(&json!("ok")), "ok");
+        assert_eq!(table(&json!(3)), "3");
+        assert_eq!(table(&Value::Null), "");
+    }
+}
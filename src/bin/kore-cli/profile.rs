@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Endpoint used when no profile or endpoint is given.
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:3000";
+
+/// Connection settings of a node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Sent as a bearer token in the `Authorization` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// Profile file, in TOML:
+///
+/// ```toml
+/// default = "local"
+///
+/// [profiles.local]
+/// endpoint = "http://localhost:3000"
+///
+/// [profiles.production]
+/// endpoint = "https://kore.example.com"
+/// token = "..."
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profiles {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Profiles {
+    /// Reads the profile file at `path`, with no profiles if it does not exist.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Invalid profile file {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!(
+                "Error reading profile file {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// Writes the profile file to `path`, creating its directory. On Unix only the user can
+    /// read it, since it holds tokens.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let error = |e: io::Error| format!("Error writing profile file {}: {}", path.display(), e);
+
+        let content = toml::to_string_pretty(self)
+            .map_err(|e| format!("Error encoding profile file: {}", e))?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(error)?;
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(path).map_err(error)?;
+        io::Write::write_all(&mut file, content.as_bytes()).map_err(error)
+    }
+
+    /// The named profile, or the default one if no name is given.
+    pub fn get(&self, name: Option<&str>) -> Result<Profile, String> {
+        let Some(name) = name.or(self.default.as_deref()) else {
+            return Ok(Profile::default());
+        };
+
+        self.profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Profile {} not found", name))
+    }
+}
+
+/// Path of the profile file, `KORE_CLI_CONFIG` or `kore/cli.toml` in the user config directory.
+pub fn profiles_path() -> Result<PathBuf, String> {
+    match env::var("KORE_CLI_CONFIG") {
+        Ok(path) => Ok(PathBuf::from(path)),
+        Err(_) => dirs::config_dir()
+            .map(|x| x.join("kore").join("cli.toml"))
+            .ok_or_else(|| "No profile file could be found".to_owned()),
+    }
+}
+
+/// Loads the named profile, or the default one if no name is given.
+///
+/// A missing profile file is only an error when a profile is requested by name.
+pub fn load(name: Option<&str>) -> Result<Profile, String> {
+    match profiles_path() {
+        Ok(path) => Profiles::read(&path)?
+            .get(name)
+            .map_err(|e| format!("{} in {}", e, path.display())),
+        Err(_) if name.is_none() => Ok(Profile::default()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir()
+            .join(format!("kore-cli-{}-{}", std::process::id(), name))
+            .join("cli.toml")
+    }
+
+    #[test]
+    fn missing_file_has_no_profiles() {
+        let profiles = Profiles::read(&temp_path("missing")).unwrap();
+        assert_eq!(profiles, Profiles::default());
+        assert_eq!(profiles.get(None).unwrap(), Profile::default());
+        assert!(profiles.get(Some("production")).is_err());
+    }
+
+    #[test]
+    fn written_profiles_are_read_back() {
+        let path = temp_path("roundtrip");
+        let mut profiles = Profiles::default();
+        profiles.profiles.insert(
+            "production".to_owned(),
+            Profile {
+                endpoint: Some("https://kore.example.com".to_owned()),
+                token: Some("secret".to_owned()),
+            },
+        );
+        profiles.default = Some("production".to_owned());
+
+        profiles.write(&path).unwrap();
+        let read = Profiles::read(&path).unwrap();
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(read, profiles);
+        assert_eq!(
+            read.get(None).unwrap().endpoint.as_deref(),
+            Some("https://kore.example.com")
+        );
+    }
+
+    #[test]
+    fn reads_the_documented_format() {
+        let profiles: Profiles = toml::from_str(
+            r#"
+            default = "local"
+
+            [profiles.local]
+            endpoint = "http://localhost:3000"
+
+            [profiles.production]
+            endpoint = "https://kore.example.com"
+            token = "..."
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profiles.get(None).unwrap().endpoint.as_deref(),
+            Some("http://localhost:3000")
+        );
+        assert_eq!(
+            profiles.get(Some("production")).unwrap().token.as_deref(),
+            Some("...")
+        );
+    }
+}
@@ -0,0 +1,597 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Read},
+};
+
+use async_zip::{Compression, ZipEntryBuilder, tokio::write::ZipFileWriter};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::io::AsyncWrite;
+use zip::ZipArchive;
+
+use crate::{
+    state::{Replay, properties_hash, replay},
+    wrappers::{
+        BundleError, BundleFile, BundleManifest, BundleReport, EventInfo, ProtocolsSignaturesInfo,
+        SignatureInfo, SignaturesInfo, SubjectInfo,
+    },
+};
+
+/// Name of the manifest within a bundle.
+pub const MANIFEST: &str = "manifest.json";
+/// Name of the subject state within a bundle.
+pub const SUBJECT: &str = "subject.json";
+/// Maximum size of a file of a bundle once decompressed.
+pub const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Maximum size of all the files of a bundle once decompressed.
+pub const MAX_BUNDLE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Path of the event with the given sn within a bundle.
+pub fn event_file(sn: u64) -> String {
+    format!("events/{}.json", sn)
+}
+
+/// Path of the signatures of the event with the given sn within a bundle.
+pub fn signatures_file(sn: u64) -> String {
+    format!("signatures/{}.json", sn)
+}
+
+/// Computes the Blake3 hash of the content of a file, encoded in base64url.
+pub fn file_hash(content: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(blake3::hash(content).as_bytes())
+}
+
+/// Event as stored in a bundle, chained to the file of the previous event by its hash.
+///
+/// The node does not expose the `hash_prev_event` of its events, which hashes their binary
+/// encoding, so the bundle chains the files of the events it holds instead.
+#[derive(Serialize, Deserialize)]
+struct BundleEvent<E> {
+    /// The hash of the file of the previous event, none for the genesis event.
+    #[serde(default)]
+    hash_prev_event: Option<String>,
+    #[serde(flatten)]
+    event: E,
+}
+
+/// Writes a zip bundle with the complete history of a subject, file by file, so that the
+/// bundle never has to fit in memory.
+///
+/// The bundle holds the subject, every event chained to the previous one, the available
+/// signatures and, last, a manifest with the hash of each file. The hashes reveal a corrupted
+/// bundle, but they are not signed, so they do not prove that the bundle was exported by the
+/// node.
+pub struct BundleWriter<W: AsyncWrite + Unpin> {
+    zip: ZipFileWriter<W>,
+    files: Vec<BundleFile>,
+    replay: Replay,
+    hash_prev_event: Option<String>,
+}
+
+impl<W: AsyncWrite + Unpin> BundleWriter<W> {
+    pub fn new(writer: W, subject: &SubjectInfo) -> Self {
+        Self {
+            zip: ZipFileWriter::with_tokio(writer),
+            files: vec![],
+            replay: Replay::new(subject.schema_id == "governance"),
+            hash_prev_event: None,
+        }
+    }
+
+    pub async fn write_subject(&mut self, subject: &SubjectInfo) -> Result<(), String> {
+        self.write(SUBJECT, subject).await
+    }
+
+    /// Writes the next event, which must follow the last one written.
+    pub async fn write_event(&mut self, event: &EventInfo) -> Result<(), String> {
+        self.replay.apply(event)?;
+        let event = BundleEvent {
+            hash_prev_event: self.hash_prev_event.take(),
+            event,
+        };
+        self.write(&event_file(event.event.sn), &event).await?;
+
+        self.hash_prev_event = self.files.last().map(|x| x.hash.clone());
+        Ok(())
+    }
+
+    pub async fn write_signatures(&mut self, signatures: &SignaturesInfo) -> Result<(), String> {
+        self.write(&signatures_file(signatures.sn), signatures)
+            .await
+    }
+
+    /// Writes the manifest of the files written so far and ends the bundle.
+    pub async fn finish(
+        mut self,
+        subject: &SubjectInfo,
+        governance_version: u64,
+    ) -> Result<(), String> {
+        let Some(state) = self.replay.state() else {
+            return Err("The bundle has no events".to_owned());
+        };
+
+        let manifest = BundleManifest {
+            subject_id: subject.subject_id.clone(),
+            governance_id: subject.governance_id.clone(),
+            governance_version,
+            sn: subject.sn,
+            properties_hash: properties_hash(state),
+            files: std::mem::take(&mut self.files),
+        };
+        self.write(MANIFEST, &manifest).await?;
+
+        self.zip
+            .close()
+            .await
+            .map_err(|e| format!("Error finishing zip: {}", e))?;
+        Ok(())
+    }
+
+    async fn write<T: Serialize>(&mut self, name: &str, value: &T) -> Result<(), String> {
+        let content = to_json(value)?;
+        self.files.push(BundleFile {
+            name: name.to_owned(),
+            hash: file_hash(&content),
+        });
+
+        let entry = ZipEntryBuilder::new(name.into(), Compression::Deflate);
+        self.zip
+            .write_entry_whole(entry, &content)
+            .await
+            .map_err(|e| format!("Error writing {}: {}", name, e))
+    }
+}
+
+/// Checks the consistency of a bundle written by [`BundleWriter`].
+///
+/// Checks the hash of every file against the manifest, which must list every file, that the
+/// events form a gapless sequence from genesis for the subject, as many as the manifest declares,
+/// each chained to the previous one by `hash_prev_event`, that replaying them yields the
+/// properties hash of the manifest and the state of the subject, and that every signature is well
+/// formed. It does not prove that the bundle is authentic: the manifest is not signed, the state
+/// hashes of the node are not part of the bundle, and signatures are not checked
+/// cryptographically, since the node signs its binary encoding.
+pub fn verify_bundle(bundle: &[u8]) -> BundleReport {
+    let mut report = BundleReport {
+        subject_id: None,
+        sn: None,
+        errors: vec![],
+    };
+
+    let files = match read_files(bundle) {
+        Ok(files) => files,
+        Err(reason) => {
+            report.errors.push(error(None, None, reason));
+            return report;
+        }
+    };
+
+    let manifest: BundleManifest = match parse(&files, MANIFEST) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            report.errors.push(e);
+            return report;
+        }
+    };
+    report.subject_id = Some(manifest.subject_id.clone());
+    report.sn = Some(manifest.sn);
+
+    for file in manifest.files.iter() {
+        match files.get(&file.name) {
+            Some(content) if file_hash(content) != file.hash => report.errors.push(error(
+                None,
+                Some(&file.name),
+                "The hash of the file does not match the manifest".to_owned(),
+            )),
+            Some(_) => {}
+            None => report.errors.push(error(
+                None,
+                Some(&file.name),
+                "The file is listed in the manifest but missing from the bundle".to_owned(),
+            )),
+        }
+    }
+
+    for name in files.keys() {
+        if name != MANIFEST && !manifest.files.iter().any(|x| x.name == *name) {
+            report.errors.push(error(
+                None,
+                Some(name),
+                "The file is in the bundle but not listed in the manifest".to_owned(),
+            ));
+        }
+    }
+
+    let event_files = numbered_files(&files, "events/");
+    if manifest.sn.checked_add(1) != Some(event_files.len() as u64) {
+        report.errors.push(error(
+            None,
+            None,
+            format!(
+                "The manifest declares sn {} but the bundle has {} events",
+                manifest.sn,
+                event_files.len()
+            ),
+        ));
+    }
+
+    let mut events = vec![];
+    let mut hash_prev_event = None;
+    for (expected, (sn, name)) in (0..).zip(event_files) {
+        if sn != expected {
+            report.errors.push(error(
+                Some(expected),
+                Some(&event_file(expected)),
+                "The file is missing, breaking the sequence of events".to_owned(),
+            ));
+            break;
+        }
+
+        let BundleEvent::<EventInfo> {
+            hash_prev_event: chained,
+            event,
+        } = match parse(&files, name) {
+            Ok(event) => event,
+            Err(e) => {
+                report.errors.push(BundleError { sn: Some(sn), ..e });
+                break;
+            }
+        };
+
+        if chained != hash_prev_event {
+            report.errors.push(error(
+                Some(sn),
+                Some(name),
+                "hash_prev_event does not match the hash of the previous event".to_owned(),
+            ));
+        }
+        hash_prev_event = files.get(name).map(|x| file_hash(x));
+
+        if event.sn != sn {
+            report.errors.push(error(
+                Some(sn),
+                Some(name),
+                format!("The event has sn {}, breaking the sequence", event.sn),
+            ));
+            break;
+        }
+
+        if event.subject_id != manifest.subject_id {
+            report.errors.push(error(
+                Some(sn),
+                Some(name),
+                format!("The event belongs to subject {}", event.subject_id),
+            ));
+        }
+        events.push(event);
+    }
+
+    let subject = match parse::<SubjectInfo>(&files, SUBJECT) {
+        Ok(subject) => Some(subject),
+        Err(e) => {
+            report.errors.push(e);
+            None
+        }
+    };
+
+    if let Some(subject) = subject
+        && report.errors.is_empty()
+    {
+        match replay(&events, subject.schema_id == "governance") {
+            Ok(state) => {
+                if properties_hash(&state) != manifest.properties_hash {
+                    report.errors.push(error(
+                        Some(manifest.sn),
+                        None,
+                        "The hash of the replayed state does not match the manifest".to_owned(),
+                    ));
+                }
+                if subject.properties != state {
+                    report.errors.push(error(
+                        Some(subject.sn),
+                        Some(SUBJECT),
+                        "The replayed state does not match the state of the subject".to_owned(),
+                    ));
+                }
+            }
+            Err(reason) => report.errors.push(error(None, None, reason)),
+        }
+    }
+
+    for (sn, name) in numbered_files(&files, "signatures/") {
+        let signatures: SignaturesInfo = match parse(&files, name) {
+            Ok(signatures) => signatures,
+            Err(e) => {
+                report.errors.push(BundleError { sn: Some(sn), ..e });
+                continue;
+            }
+        };
+
+        let protocols = [
+            ("evaluation", signatures.signatures_eval.unwrap_or_default()),
+            ("approval", signatures.signatures_appr.unwrap_or_default()),
+            ("validation", signatures.signatures_vali),
+        ];
+        for (protocol, set) in protocols {
+            for signature in set {
+                let ProtocolsSignaturesInfo::Signature(signature) = signature else {
+                    continue;
+                };
+
+                if let Err(reason) = check_signature(&signature) {
+                    report.errors.push(error(
+                        Some(sn),
+                        Some(name),
+                        format!(
+                            "Invalid {} signature of {}: {}",
+                            protocol, signature.signer, reason
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Files of a bundle in `directory` named by a sequence number, sorted by it.
+fn numbered_files<'a>(
+    files: &'a HashMap<String, Vec<u8>>,
+    directory: &str,
+) -> BTreeMap<u64, &'a str> {
+    files
+        .keys()
+        .filter_map(|name| {
+            let sn = name.strip_prefix(directory)?.strip_suffix(".json")?;
+            Some((sn.parse().ok()?, name.as_str()))
+        })
+        .collect()
+}
+
+/// Reads the files of a zip bundle, refusing files larger than [`MAX_FILE_SIZE`] and bundles
+/// larger than [`MAX_BUNDLE_SIZE`] once decompressed.
+fn read_files(bundle: &[u8]) -> Result<HashMap<String, Vec<u8>>, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(bundle)).map_err(|e| format!("Invalid zip file: {}", e))?;
+
+    let mut files = HashMap::new();
+    let mut total: u64 = 0;
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|e| format!("Error reading zip file: {}", e))?;
+        let name = file.name().to_owned();
+        if file.size() > MAX_FILE_SIZE {
+            return Err(format!(
+                "{} is larger than {} bytes once decompressed",
+                name, MAX_FILE_SIZE
+            ));
+        }
+
+        let mut content = vec![];
+        file.take(MAX_FILE_SIZE + 1)
+            .read_to_end(&mut content)
+            .map_err(|e| format!("Error reading {}: {}", name, e))?;
+        if content.len() as u64 > MAX_FILE_SIZE {
+            return Err(format!(
+                "{} is larger than {} bytes once decompressed",
+                name, MAX_FILE_SIZE
+            ));
+        }
+
+        total = total.saturating_add(content.len() as u64);
+        if total > MAX_BUNDLE_SIZE {
+            return Err(format!(
+                "The bundle is larger than {} bytes once decompressed",
+                MAX_BUNDLE_SIZE
+            ));
+        }
+        files.insert(name, content);
+    }
+
+    Ok(files)
+}
+
+fn parse<T: DeserializeOwned>(
+    files: &HashMap<String, Vec<u8>>,
+    name: &str,
+) -> Result<T, BundleError> {
+    let Some(content) = files.get(name) else {
+        return Err(error(None, Some(name), "The file is missing".to_owned()));
+    };
+
+    serde_json::from_slice(content)
+        .map_err(|e| error(None, Some(name), format!("Invalid content: {}", e)))
+}
+
+fn check_signature(signature: &SignatureInfo) -> Result<(), String> {
+    decode_identifier(&signature.signer, "E", 32).map_err(|e| format!("signer {}", e))?;
+    decode_identifier(&signature.content_hash, "J", 32)
+        .map_err(|e| format!("content hash {}", e))?;
+    decode_identifier(&signature.value, "SE", 64).map_err(|e| format!("value {}", e))?;
+    Ok(())
+}
+
+fn decode_identifier(identifier: &str, prefix: &str, len: usize) -> Result<(), String> {
+    let Some(raw) = identifier.strip_prefix(prefix) else {
+        return Err(format!("does not start with {}", prefix));
+    };
+
+    match URL_SAFE_NO_PAD.decode(raw) {
+        Ok(bytes) if bytes.len() == len => Ok(()),
+        Ok(bytes) => Err(format!("has {} bytes instead of {}", bytes.len(), len)),
+        Err(e) => Err(format!("is not valid base64: {}", e)),
+    }
+}
+
+fn error(sn: Option<u64>, file: Option<&str>, reason: String) -> BundleError {
+    BundleError {
+        sn,
+        file: file.map(str::to_owned),
+        reason,
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| format!("Error serializing bundle: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::wrappers::{CreateRequestInfo, EventRequestInfo, FactRequestInfo, Namespace};
+
+    fn subject() -> SubjectInfo {
+        SubjectInfo {
+            subject_id: "Jsubject".to_owned(),
+            governance_id: "Jgovernance".to_owned(),
+            genesis_gov_version: 0,
+            namespace: String::new(),
+            schema_id: "example".to_owned(),
+            owner: "Eowner".to_owned(),
+            creator: "Eowner".to_owned(),
+            active: true,
+            sn: 2,
+            properties: json!({ "a": 2 }),
+            new_owner: None,
+            name: String::new(),
+            description: String::new(),
+        }
+    }
+
+    fn events() -> Vec<EventInfo> {
+        let genesis = EventInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn: 0,
+            patch: Some(json!({ "a": 0 })),
+            error: None,
+            event_req: EventRequestInfo::Create(CreateRequestInfo {
+                governance_id: "Jgovernance".to_owned(),
+                schema_id: "example".to_owned(),
+                namespace: Namespace::from(""),
+                name: None,
+                description: None,
+            }),
+            succes: true,
+        };
+        let fact = |sn: u64| EventInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn,
+            patch: Some(json!([{ "op": "replace", "path": "/a", "value": sn }])),
+            error: None,
+            event_req: EventRequestInfo::Fact(FactRequestInfo {
+                subject_id: "Jsubject".to_owned(),
+                payload: json!({}),
+            }),
+            succes: true,
+        };
+        vec![genesis, fact(1), fact(2)]
+    }
+
+    /// The files of a bundle of [`subject`] with [`events`].
+    async fn bundle_files() -> HashMap<String, Vec<u8>> {
+        let subject = subject();
+        let mut bundle = vec![];
+        let mut writer = BundleWriter::new(&mut bundle, &subject);
+        writer.write_subject(&subject).await.unwrap();
+        for event in events() {
+            writer.write_event(&event).await.unwrap();
+        }
+        writer.finish(&subject, 0).await.unwrap();
+
+        read_files(&bundle).unwrap()
+    }
+
+    async fn zip(files: &HashMap<String, Vec<u8>>) -> Vec<u8> {
+        let mut names: Vec<&String> = files.keys().collect();
+        names.sort();
+
+        let mut bundle = vec![];
+        let mut zip = ZipFileWriter::with_tokio(&mut bundle);
+        for name in names {
+            let entry = ZipEntryBuilder::new(name.clone().into(), Compression::Deflate);
+            zip.write_entry_whole(entry, &files[name]).await.unwrap();
+        }
+        zip.close().await.unwrap();
+        bundle
+    }
+
+    /// Replaces a file of the bundle along with its hash in the manifest.
+    fn replace(files: &mut HashMap<String, Vec<u8>>, name: &str, content: Vec<u8>) {
+        let mut manifest: BundleManifest = serde_json::from_slice(&files[MANIFEST]).unwrap();
+        for file in manifest.files.iter_mut().filter(|x| x.name == name) {
+            file.hash = file_hash(&content);
+        }
+        files.insert(name.to_owned(), content);
+        files.insert(MANIFEST.to_owned(), to_json(&manifest).unwrap());
+    }
+
+    fn has_error(report: &BundleReport, sn: Option<u64>, file: &str) -> bool {
+        report
+            .errors
+            .iter()
+            .any(|x| x.sn == sn && x.file.as_deref() == Some(file))
+    }
+
+    #[tokio::test]
+    async fn written_bundles_verify() {
+        let files = bundle_files().await;
+
+        let event: Value = serde_json::from_slice(&files[&event_file(1)]).unwrap();
+        assert_eq!(
+            event["hash_prev_event"],
+            json!(file_hash(&files[&event_file(0)]))
+        );
+
+        let report = verify_bundle(&zip(&files).await);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.subject_id.as_deref(), Some("Jsubject"));
+        assert_eq!(report.sn, Some(2));
+    }
+
+    #[tokio::test]
+    async fn tampered_events_break_the_chain() {
+        let mut files = bundle_files().await;
+        let mut event: Value = serde_json::from_slice(&files[&event_file(1)]).unwrap();
+        event["patch"] = json!([{ "op": "replace", "path": "/a", "value": 5 }]);
+        replace(&mut files, &event_file(1), to_json(&event).unwrap());
+
+        let report = verify_bundle(&zip(&files).await);
+        assert!(
+            has_error(&report, Some(2), &event_file(2)),
+            "{:?}",
+            report.errors
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_events_are_reported() {
+        let mut files = bundle_files().await;
+        files.remove(&event_file(1));
+
+        let report = verify_bundle(&zip(&files).await);
+        assert!(
+            has_error(&report, None, &event_file(1)),
+            "{:?}",
+            report.errors
+        );
+        assert!(
+            has_error(&report, Some(1), &event_file(1)),
+            "{:?}",
+            report.errors
+        );
+    }
+
+    #[tokio::test]
+    async fn files_missing_from_the_manifest_are_reported() {
+        let mut files = bundle_files().await;
+        files.insert("notes.txt".to_owned(), b"unlisted".to_vec());
+
+        let report = verify_bundle(&zip(&files).await);
+        assert!(has_error(&report, None, "notes.txt"), "{:?}", report.errors);
+        assert_eq!(report.errors.len(), 1);
+    }
+}
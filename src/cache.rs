@@ -0,0 +1,401 @@
+use std::{
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use kore_bridge::{Bridge, model::BridgeEventRequest};
+use moka::{Expiry, future::Cache, policy::EvictionPolicy};
+
+use crate::{
+    enviroment::{build_cache_max_memory, build_cache_ttl},
+    error::Error,
+    server::POLL_INTERVAL,
+    wrappers::{CacheMetrics, GovsData, RegisterDataSubj, RouteCacheMetrics, SubjectInfo},
+};
+
+/// Most cached subjects checked against the node every poll interval.
+const MAX_REVALIDATIONS: usize = 16;
+
+/// Bridge read whose responses are cached.
+#[derive(Debug, Clone, Copy)]
+enum Route {
+    Governances,
+    Subjects,
+    State,
+}
+
+impl Route {
+    const ALL: [Route; 3] = [Route::Governances, Route::Subjects, Route::State];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Route::Governances => "GOVERNANCES",
+            Route::Subjects => "SUBJECTS",
+            Route::State => "STATE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Governances {
+        active: Option<bool>,
+    },
+    Subjects {
+        governance_id: String,
+        active: Option<bool>,
+        schema: Option<String>,
+    },
+    Subject(String),
+}
+
+impl Key {
+    fn route(&self) -> Route {
+        match self {
+            Key::Governances { .. } => Route::Governances,
+            Key::Subjects { .. } => Route::Subjects,
+            Key::Subject(_) => Route::State,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Entry {
+    Governances(Arc<Vec<GovsData>>),
+    Subjects(Arc<Vec<RegisterDataSubj>>),
+    Subject(Arc<SubjectInfo>),
+}
+
+impl Entry {
+    /// Approximate memory used by the entry, the size of its JSON encoding.
+    fn weight(&self) -> u32 {
+        let size = match self {
+            Entry::Governances(x) => serde_json::to_vec(x.as_ref()),
+            Entry::Subjects(x) => serde_json::to_vec(x.as_ref()),
+            Entry::Subject(x) => serde_json::to_vec(x.as_ref()),
+        }
+        .map(|x| x.len())
+        .unwrap_or_default();
+
+        u32::try_from(size).unwrap_or(u32::MAX)
+    }
+}
+
+/// Expires each entry after the time to live of its route.
+struct RouteExpiry([Duration; 3]);
+
+impl Expiry<Key, Entry> for RouteExpiry {
+    fn expire_after_create(
+        &self,
+        key: &Key,
+        _value: &Entry,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(self.0[key.route() as usize])
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Bounded LRU cache of the bridge reads behind the governance and subject listings and the
+/// state of a subject.
+///
+/// Entries expire after the time to live of their route, `KORE_HTTP_CACHE_TTL_<ROUTE>` in
+/// milliseconds, and are evicted when the cache goes over `KORE_HTTP_CACHE_MAX_MEMORY` bytes,
+/// 0 disabling it. The cached subjects are checked against the node in turns every poll
+/// interval, and a subject with new events is dropped along with the listings it appears in, as
+/// is whatever an event request sent through the API may change.
+pub struct ResponseCache {
+    entries: Option<Cache<Key, Entry>>,
+    max_memory: u64,
+    ttl: [Duration; 3],
+    counters: [Counters; 3],
+}
+
+impl ResponseCache {
+    /// Builds the cache from the environment and starts watching the cached subjects.
+    pub fn build(bridge: Arc<Bridge>) -> Arc<Self> {
+        let max_memory = build_cache_max_memory();
+        let ttl = Route::ALL.map(|route| build_cache_ttl(route.name()));
+
+        let entries = (max_memory > 0).then(|| {
+            Cache::builder()
+                .max_capacity(max_memory)
+                .weigher(|_key: &Key, entry: &Entry| entry.weight())
+                .eviction_policy(EvictionPolicy::lru())
+                .expire_after(RouteExpiry(ttl))
+                .build()
+        });
+
+        let cache = Arc::new(Self {
+            entries,
+            max_memory,
+            ttl,
+            counters: Default::default(),
+        });
+
+        if cache.entries.is_some() {
+            tokio::spawn(watch(Arc::downgrade(&cache), bridge));
+        }
+        cache
+    }
+
+    /// Governances to which the node belongs.
+    pub async fn governances(
+        &self,
+        bridge: &Bridge,
+        active: Option<bool>,
+    ) -> Result<Vec<GovsData>, Error> {
+        let entry = self
+            .get_or_load(Key::Governances { active }, async {
+                match bridge.get_all_govs(active).await {
+                    Ok(response) => Ok(Entry::Governances(Arc::new(
+                        response.into_iter().map(GovsData::from).collect(),
+                    ))),
+                    Err(e) => Err(e.to_string()),
+                }
+            })
+            .await?;
+
+        match entry {
+            Entry::Governances(govs) => Ok(govs.as_ref().clone()),
+            _ => Err(invalid_entry()),
+        }
+    }
+
+    /// Subjects of a governance.
+    pub async fn subjects(
+        &self,
+        bridge: &Bridge,
+        governance_id: String,
+        active: Option<bool>,
+        schema: Option<String>,
+    ) -> Result<Vec<RegisterDataSubj>, Error> {
+        let key = Key::Subjects {
+            governance_id: governance_id.clone(),
+            active,
+            schema: schema.clone(),
+        };
+        let entry = self
+            .get_or_load(key, async {
+                match bridge.get_all_subjs(governance_id, active, schema).await {
+                    Ok(response) => Ok(Entry::Subjects(Arc::new(
+                        response.into_iter().map(RegisterDataSubj::from).collect(),
+                    ))),
+                    Err(e) => Err(e.to_string()),
+                }
+            })
+            .await?;
+
+        match entry {
+            Entry::Subjects(subjects) => Ok(subjects.as_ref().clone()),
+            _ => Err(invalid_entry()),
+        }
+    }
+
+    /// Current state of a subject.
+    pub async fn subject(&self, bridge: &Bridge, subject_id: String) -> Result<SubjectInfo, Error> {
+        let entry = self
+            .get_or_load(Key::Subject(subject_id.clone()), async {
+                match bridge.get_subject(subject_id).await {
+                    Ok(response) => Ok(Entry::Subject(Arc::new(SubjectInfo::from(response)))),
+                    Err(e) => Err(e.to_string()),
+                }
+            })
+            .await?;
+
+        match entry {
+            Entry::Subject(subject) => Ok(subject.as_ref().clone()),
+            _ => Err(invalid_entry()),
+        }
+    }
+
+    /// Hits and misses of each route and the size of the cache.
+    pub fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            enabled: self.entries.is_some(),
+            entries: self
+                .entries
+                .as_ref()
+                .map(Cache::entry_count)
+                .unwrap_or_default(),
+            memory: self
+                .entries
+                .as_ref()
+                .map(Cache::weighted_size)
+                .unwrap_or_default(),
+            max_memory: self.max_memory,
+            routes: Route::ALL
+                .iter()
+                .map(|route| {
+                    let counters = &self.counters[*route as usize];
+                    RouteCacheMetrics {
+                        route: route.name().to_lowercase(),
+                        ttl: self.ttl[*route as usize].as_millis() as u64,
+                        hits: counters.hits.load(Ordering::Relaxed),
+                        misses: counters.misses.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// The cached entry of `key`, loading it on a miss. Concurrent misses of the same key wait
+    /// for a single load instead of each asking the node.
+    async fn get_or_load(
+        &self,
+        key: Key,
+        load: impl Future<Output = Result<Entry, String>>,
+    ) -> Result<Entry, Error> {
+        let Some(entries) = &self.entries else {
+            return load.await.map_err(Error::Kore);
+        };
+
+        let counters = &self.counters[key.route() as usize];
+        let loaded = AtomicBool::new(false);
+        let entry = entries
+            .try_get_with(key, async {
+                loaded.store(true, Ordering::Relaxed);
+                load.await
+            })
+            .await
+            .map_err(|e| Error::Kore(e.as_ref().clone()))?;
+
+        if loaded.load(Ordering::Relaxed) {
+            counters.misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            counters.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(entry)
+    }
+
+    /// Drops what an event request sent through the API may change: the subject and the
+    /// listings it appears in or, for a new subject, the subjects of its governance and, for a
+    /// new governance, the governances.
+    pub async fn invalidate_request(&self, request: &BridgeEventRequest) {
+        let subject_id = match request {
+            BridgeEventRequest::Create(create) => {
+                let is_governance = create.schema_id == "governance";
+                self.invalidate_where(|key, _| match key {
+                    Key::Subjects { governance_id, .. } => *governance_id == create.governance_id,
+                    Key::Governances { .. } => is_governance,
+                    Key::Subject(_) => false,
+                })
+                .await;
+                return;
+            }
+            BridgeEventRequest::Fact(x) => &x.subject_id,
+            BridgeEventRequest::Transfer(x) => &x.subject_id,
+            BridgeEventRequest::Confirm(x) => &x.subject_id,
+            BridgeEventRequest::Reject(x) => &x.subject_id,
+            BridgeEventRequest::EOL(x) => &x.subject_id,
+        };
+
+        self.invalidate_where(|key, entry| match (key, entry) {
+            (Key::Subject(id), _) => id == subject_id,
+            (Key::Subjects { governance_id, .. }, Entry::Subjects(subjects)) => {
+                governance_id == subject_id || subjects.iter().any(|x| x.subject_id == *subject_id)
+            }
+            (Key::Governances { .. }, Entry::Governances(govs)) => {
+                govs.iter().any(|x| x.governance_id == *subject_id)
+            }
+            _ => false,
+        })
+        .await;
+    }
+
+    /// Drops a subject and the listings it appears in: the subjects of its governance and, for
+    /// a governance, the governances and its own subjects.
+    async fn invalidate(&self, subject: &SubjectInfo) {
+        let is_governance = subject.schema_id == "governance";
+
+        self.invalidate_where(|key, _| match key {
+            Key::Subject(subject_id) => *subject_id == subject.subject_id,
+            Key::Subjects { governance_id, .. } => {
+                *governance_id == subject.governance_id
+                    || (is_governance && *governance_id == subject.subject_id)
+            }
+            Key::Governances { .. } => is_governance,
+        })
+        .await;
+    }
+
+    async fn invalidate_where(&self, stale: impl Fn(&Key, &Entry) -> bool) {
+        let Some(entries) = &self.entries else {
+            return;
+        };
+
+        let keys: Vec<Arc<Key>> = entries
+            .iter()
+            .filter(|(key, entry)| stale(key, entry))
+            .map(|(key, _)| key)
+            .collect();
+
+        for key in keys {
+            entries.invalidate(key.as_ref()).await;
+        }
+    }
+}
+
+fn invalid_entry() -> Error {
+    Error::Kore("The response cache holds an entry of another route".to_owned())
+}
+
+/// Drops the cached subjects whose sn or status no longer match the node, until the cache
+/// goes away.
+///
+/// At most `MAX_REVALIDATIONS` subjects are checked every poll interval, taking turns in the
+/// order of their ids, so a large cache does not flood the node.
+async fn watch(cache: Weak<ResponseCache>, bridge: Arc<Bridge>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last: Option<String> = None;
+
+    loop {
+        interval.tick().await;
+
+        let Some(cache) = cache.upgrade() else {
+            return;
+        };
+        let Some(entries) = &cache.entries else {
+            return;
+        };
+
+        let mut subjects: Vec<Arc<SubjectInfo>> = entries
+            .iter()
+            .filter_map(|(_, entry)| match entry {
+                Entry::Subject(subject) => Some(subject),
+                _ => None,
+            })
+            .collect();
+        subjects.sort_unstable_by(|a, b| a.subject_id.cmp(&b.subject_id));
+
+        // Starts after the last subject checked on the previous interval.
+        let start = last.as_ref().map_or(0, |last| {
+            subjects.partition_point(|x| x.subject_id <= *last)
+        });
+        let turn: Vec<_> = subjects[start..]
+            .iter()
+            .chain(&subjects[..start])
+            .take(MAX_REVALIDATIONS)
+            .cloned()
+            .collect();
+        last = turn.last().map(|x| x.subject_id.clone());
+
+        for cached in turn {
+            let changed = match bridge.get_subject(cached.subject_id.clone()).await {
+                Ok(subject) => subject.sn != cached.sn || subject.active != cached.active,
+                Err(_) => true,
+            };
+            if changed {
+                cache.invalidate(&cached).await;
+            }
+        }
+    }
+}
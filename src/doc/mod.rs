@@ -1,12 +1,16 @@
 use crate::{
     server::*,
     wrappers::{
-        ApprovalReqInfo, ApproveInfo, Config, ConfirmRequestInfo, ControlListConfig,
-        CreateRequestInfo, EOLRequestInfo, EventInfo, EventRequestInfo, FactInfo, FactRequestInfo,
-        GovsData, KoreConfig, Namespace, NetworkConfig, Paginator, PaginatorEvents, ProtocolsError,
-        ProtocolsSignaturesInfo, RegisterDataSubj, RejectRequestInfo, RequestData, RequestInfo,
-        RoutingConfig, RoutingNode, SignatureInfo, SignaturesInfo, SignedInfo, SubjectInfo,
-        TellConfig, TimeOutResponseInfo, TransferRequestInfo, TransferSubject,
+        ApprovalReqInfo, ApproveInfo, BundleError, BundleFile, BundleManifest, BundleReport,
+        CacheMetrics, Config, ConfirmRequestInfo, Contract, ControlListConfig, CreateRequestInfo,
+        CursorEvents, EOLRequestInfo, EventInfo, EventRequestInfo, EventType, EventsPage,
+        ExportFormat, FactInfo, FactRequestInfo, FieldChange, Governance, GovsData, KoreConfig,
+        Member, Namespace, NetworkConfig, Paginator, PaginatorEvents, Policy, ProtocolQuorum,
+        ProtocolsError, ProtocolsSignaturesInfo, Quorum, RegisterDataSubj, RejectRequestInfo,
+        RequestData, RequestInfo, Role, RoleType, RouteCacheMetrics, RoutingConfig, RoutingNode,
+        Schema, SchemaEnum, SignatureInfo, SignaturesInfo, SignaturesQuorum, SignedInfo, Signer,
+        SortField, StateDiff, StateResponse, SubjectInfo, SubjectState, TellConfig,
+        TimeOutResponseInfo, TimedOutSigner, TransferRequestInfo, TransferSubject, Validation, Who,
     },
 };
 use utoipa::OpenApi;
@@ -25,7 +29,7 @@ use utoipa::OpenApi;
     info(
         title = "Kore HTTP",
         description = "This API provides interaction with Kore Ledger nodes using the HTTP protocol. It allows sending and retrieving various types of requests and managing subjects. The API is documented with OpenAPI for easy integration and use.",
-        version = "0.3.0",
+        version = env!("CARGO_PKG_VERSION"),
         contact(
             name = "Kore Information",
             url = "https://www.kore-ledger.net/",
@@ -36,6 +40,9 @@ use utoipa::OpenApi;
             url = "https://opensource.org/licenses/MIT"
         )
     ),
+    servers(
+        (url = "/v1", description = "Current version of the API")
+    ),
     paths(
         send_event_request,
         get_request_state,
@@ -48,10 +55,16 @@ use utoipa::OpenApi;
         update_subject,
         manual_distribution,
         get_all_govs,
+        get_governance_members,
+        get_governance_roles,
+        get_governance_schemas,
+        get_governance_policies,
         get_all_subjects,
         get_events,
         get_state,
+        get_state_diff,
         get_signatures,
+        get_signatures_quorum,
         get_controller_id,
         get_peer_id,
         get_first_or_end_events,
@@ -59,14 +72,44 @@ use utoipa::OpenApi;
         check_transfer,
         get_config,
         get_keys,
-        get_pending_transfers
+        get_pending_transfers,
+        export_subject,
+        verify_bundle_request,
+        get_subject,
+        get_subject_diff,
+        get_subject_events,
+        get_subject_event,
+        get_subject_signatures,
+        get_subject_signatures_quorum,
+        get_subject_witnesses,
+        put_subject_witnesses,
+        delete_subject_witnesses,
+        get_subject_approval,
+        patch_subject_approval,
+        post_subject_update,
+        post_subject_distribution,
+        post_subject_transfer_check,
+        get_governances,
+        get_governance_subjects,
+        post_request,
+        get_request,
+        get_transfers,
+        get_cache_metrics,
+        export_subject_events,
+        export_governance_events,
+        get_readiness
     ),
     components(
         schemas(
             SubjectQuery,
             GovQuery,
+            RoleQuery,
             EventsQuery,
+            EventsCursorQuery,
             EventSnQuery,
+            StateQuery,
+            SignaturesQuery,
+            DiffQuery,
             EventFirstLastQuery,
             PaginatorEvents,
             EventInfo,
@@ -100,7 +143,38 @@ use utoipa::OpenApi;
             RoutingConfig,
             ControlListConfig,
             RoutingNode,
-            TransferSubject
+            TransferSubject,
+            Governance,
+            Member,
+            Role,
+            RoleType,
+            Who,
+            SchemaEnum,
+            Schema,
+            Contract,
+            Policy,
+            Validation,
+            Quorum,
+            EventType,
+            CursorEvents,
+            EventsPage,
+            SortField,
+            SubjectState,
+            StateResponse,
+            StateDiff,
+            FieldChange,
+            BundleManifest,
+            BundleFile,
+            BundleReport,
+            BundleError,
+            SignaturesQuorum,
+            ProtocolQuorum,
+            Signer,
+            TimedOutSigner,
+            CacheMetrics,
+            RouteCacheMetrics,
+            ExportQuery,
+            ExportFormat
         )
     ),
     tags(
@@ -1,4 +1,4 @@
-use std::env;
+use std::{env, time::Duration};
 
 pub fn build_address_http() -> String {
     env::var("KORE_HTTP_ADDRESS").unwrap_or("0.0.0.0:3000".to_owned())
@@ -8,6 +8,18 @@ pub fn build_address_https() -> String {
     env::var("KORE_HTTPS_ADDRESS").unwrap_or_default()
 }
 
+pub fn build_address_grpc() -> String {
+    env::var("KORE_GRPC_ADDRESS").unwrap_or_default()
+}
+
+pub fn build_listeners() -> String {
+    env::var("KORE_HTTP_LISTENERS").unwrap_or_default()
+}
+
+pub fn build_https_public_url() -> String {
+    env::var("KORE_HTTPS_PUBLIC_URL").unwrap_or_default()
+}
+
 pub fn build_https_cert() -> String {
     env::var("KORE_HTTPS_CERT").unwrap_or_default()
 }
@@ -19,3 +31,96 @@ pub fn build_https_private_key() -> String {
 pub fn build_doc() -> bool {
     env::var("KORE_HTTPS_DOC").unwrap_or_default() == "true"
 }
+
+pub fn build_deprecated_since() -> String {
+    env::var("KORE_HTTP_DEPRECATED_SINCE").unwrap_or("@1792281600".to_owned())
+}
+
+pub fn build_sunset() -> String {
+    env::var("KORE_HTTP_SUNSET").unwrap_or("Fri, 16 Apr 2027 00:00:00 GMT".to_owned())
+}
+
+pub fn build_cache_max_memory() -> u64 {
+    env::var("KORE_HTTP_CACHE_MAX_MEMORY")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(32 * 1024 * 1024)
+}
+
+pub fn build_cache_ttl(route: &str) -> Duration {
+    env::var(format!("KORE_HTTP_CACHE_TTL_{}", route))
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(5))
+}
+
+pub fn build_compression_min_size() -> u16 {
+    env::var("KORE_HTTP_COMPRESSION_MIN_SIZE")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(1024)
+}
+
+/// Maximum size in bytes of the request bodies of a group of routes: `DEFAULT`, `REQUESTS` for
+/// event requests and fact dry runs, or `BUNDLES` for the verification of bundles.
+pub fn build_body_limit(group: &str) -> usize {
+    let default = match group {
+        "REQUESTS" => 2 * 1024 * 1024,
+        "BUNDLES" => 16 * 1024 * 1024,
+        _ => 64 * 1024,
+    };
+
+    env::var(format!("KORE_HTTP_BODY_LIMIT_{}", group))
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(default)
+}
+
+pub fn build_body_timeout() -> Duration {
+    env::var("KORE_HTTP_BODY_TIMEOUT")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(10))
+}
+
+pub fn build_request_timeout() -> Duration {
+    env::var("KORE_HTTP_REQUEST_TIMEOUT")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(30))
+}
+
+pub fn build_max_concurrency() -> usize {
+    env::var("KORE_HTTP_MAX_CONCURRENCY")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(512)
+}
+
+pub fn build_max_streams() -> usize {
+    env::var("KORE_HTTP_MAX_STREAMS")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(64)
+}
+
+pub fn build_shutdown_delay() -> Duration {
+    env::var("KORE_HTTP_SHUTDOWN_DELAY")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::ZERO)
+}
+
+pub fn build_shutdown_timeout() -> Duration {
+    env::var("KORE_HTTP_SHUTDOWN_TIMEOUT")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(Duration::from_secs(30))
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use axum::{
     Json,
     http::StatusCode,
@@ -7,12 +9,28 @@ use axum::{
 // Error
 pub enum Error {
     Kore(String),
+    BadRequest(String),
+    PreconditionFailed(String),
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         match self {
             Error::Kore(error) => (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response(),
+            Error::BadRequest(error) => (StatusCode::BAD_REQUEST, Json(error)).into_response(),
+            Error::PreconditionFailed(error) => {
+                (StatusCode::PRECONDITION_FAILED, Json(error)).into_response()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Kore(error) | Error::BadRequest(error) | Error::PreconditionFailed(error) => {
+                f.write_str(error)
+            }
         }
     }
 }
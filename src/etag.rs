@@ -0,0 +1,109 @@
+use axum::{
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+use crate::error::Error;
+
+/// `Cache-Control` of representations that never change, such as past events.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `Cache-Control` of representations that change with new events, which caches must
+/// revalidate with their entity tag before reusing.
+const REVALIDATE: &str = "no-cache";
+
+/// Strong entity tag of a representation of the subject `subject_id` at `sn`, such as
+/// `"Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w.3"`.
+///
+/// Representations that only hold part of the subject, such as a projection of its state, give
+/// a `variant` that is hashed into a third component of the tag.
+pub fn entity_tag(subject_id: &str, sn: u64, variant: Option<&str>) -> String {
+    match variant {
+        Some(variant) => format!(
+            "\"{}.{}.{}\"",
+            subject_id,
+            sn,
+            URL_SAFE_NO_PAD.encode(&blake3::hash(variant.as_bytes()).as_bytes()[..12])
+        ),
+        None => format!("\"{}.{}\"", subject_id, sn),
+    }
+}
+
+/// Whether the `If-None-Match` header of a request matches `tag`, using the weak comparison
+/// that the header requires.
+pub fn none_match(headers: &HeaderMap, tag: &str) -> bool {
+    if_header(headers, header::IF_NONE_MATCH).is_some_and(|tags| {
+        tags.iter()
+            .any(|x| *x == "*" || x.trim_start_matches("W/") == tag)
+    })
+}
+
+/// Checks the `If-Match` header of a write on the subject `subject_id`, whose current sn is
+/// `sn`, or `None` if the subject does not exist yet.
+///
+/// Any strong tag of a representation of the current version of the subject matches, whatever
+/// its variant, so clients can send back the tag of a projection of the state.
+pub fn check_if_match(headers: &HeaderMap, subject_id: &str, sn: Option<u64>) -> Result<(), Error> {
+    let Some(tags) = if_header(headers, header::IF_MATCH) else {
+        return Ok(());
+    };
+
+    let matches = sn.is_some_and(|sn| {
+        let current = entity_tag(subject_id, sn, None);
+        let prefix = format!("{}.", current.trim_end_matches('"'));
+        tags.iter()
+            .any(|x| *x == "*" || *x == current || x.starts_with(&prefix))
+    });
+
+    if matches {
+        Ok(())
+    } else {
+        Err(Error::PreconditionFailed(match sn {
+            Some(sn) => format!(
+                "The subject {} has changed, its current version is {}",
+                subject_id,
+                entity_tag(subject_id, sn, None)
+            ),
+            None => format!("The subject {} does not exist", subject_id),
+        }))
+    }
+}
+
+/// Response for a request whose `If-None-Match` matched `tag`.
+pub fn not_modified(tag: &str, immutable: bool) -> Response {
+    tagged(StatusCode::NOT_MODIFIED, tag, immutable)
+}
+
+/// Adds the entity tag and the `Cache-Control` header to a response.
+pub fn tagged(response: impl IntoResponse, tag: &str, immutable: bool) -> Response {
+    let mut response = response.into_response();
+    let headers = response.headers_mut();
+
+    if let Ok(tag) = HeaderValue::from_str(tag) {
+        headers.insert(header::ETAG, tag);
+    }
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(if immutable { IMMUTABLE } else { REVALIDATE }),
+    );
+    response
+}
+
+/// The entity tags of a conditional header, or `None` if the request does not have it.
+fn if_header(headers: &HeaderMap, name: header::HeaderName) -> Option<Vec<&str>> {
+    let values: Vec<&str> = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .collect();
+
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
@@ -0,0 +1,223 @@
+use std::{io, sync::Arc, vec::IntoIter};
+
+use axum::body::Body;
+use bytes::Bytes;
+use futures_util::{StreamExt, future, stream};
+use kore_bridge::Bridge;
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    bundle::BundleWriter,
+    pagination::EVENTS_BATCH,
+    server::get_events_batch,
+    wrappers::{EventInfo, EventRequestInfo, EventType, ExportFormat, SignaturesInfo, SubjectInfo},
+};
+
+/// Size of the buffer between the task writing a bundle and the body that streams it.
+const BUNDLE_BUFFER: usize = 64 * 1024;
+
+/// Columns of the CSV export, with the request of each event flattened into the fields of
+/// every kind of request.
+const CSV_COLUMNS: [&str; 15] = [
+    "subject_id",
+    "sn",
+    "success",
+    "event_type",
+    "governance_id",
+    "schema_id",
+    "namespace",
+    "name",
+    "description",
+    "new_owner",
+    "name_old_owner",
+    "payload",
+    "patch",
+    "evaluation_error",
+    "validation_error",
+];
+
+struct ExportState {
+    bridge: Arc<Bridge>,
+    subjects: IntoIter<String>,
+    /// Subject being exported, with its next batch of events and the sn of its last event.
+    current: Option<(String, u64, u64)>,
+    /// Events of the last batch read that are still to be written.
+    events: IntoIter<EventInfo>,
+    failed: bool,
+}
+
+/// Streams the events of the given subjects, one subject after another, as the body of a
+/// response encoded in `format`.
+///
+/// Events are read from the node in batches of [`EVENTS_BATCH`] and each batch is written before
+/// the next one is read, so memory does not grow with the number of events. Only the events up
+/// to the sn that each subject has when its export starts are included. An error reading from
+/// the node ends the body early.
+pub fn export_events(bridge: Arc<Bridge>, subject_ids: Vec<String>, format: ExportFormat) -> Body {
+    let state = ExportState {
+        bridge,
+        subjects: subject_ids.into_iter(),
+        current: None,
+        events: Vec::new().into_iter(),
+        failed: false,
+    };
+
+    let header = match format {
+        ExportFormat::Ndjson => None,
+        ExportFormat::Csv => Some(Ok(csv_line(CSV_COLUMNS.map(str::to_owned)))),
+    };
+    let events = stream::unfold(state, next_event).map(move |event| {
+        event.map(|event| match format {
+            ExportFormat::Ndjson => ndjson_line(&event),
+            ExportFormat::Csv => csv_line(csv_record(&event)),
+        })
+    });
+
+    Body::from_stream(stream::iter(header).chain(events))
+}
+
+/// Content type of an export in `format`.
+pub fn content_type(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Ndjson => "application/x-ndjson",
+        ExportFormat::Csv => "text/csv; charset=utf-8",
+    }
+}
+
+async fn next_event(mut state: ExportState) -> Option<(Result<EventInfo, io::Error>, ExportState)> {
+    loop {
+        if state.failed {
+            return None;
+        }
+
+        if let Some(event) = state.events.next() {
+            return Some((Ok(event), state));
+        }
+
+        if let Some((subject_id, batch, last_sn)) = state.current.take()
+            && batch <= last_sn / EVENTS_BATCH
+        {
+            match get_events_batch(&state.bridge, &subject_id, batch).await {
+                Ok(events) => {
+                    let events: Vec<_> = events.into_iter().filter(|x| x.sn <= last_sn).collect();
+                    state.events = events.into_iter();
+                    state.current = Some((subject_id, batch + 1, last_sn));
+                    continue;
+                }
+                Err(e) => {
+                    state.failed = true;
+                    return Some((Err(io::Error::other(e.to_string())), state));
+                }
+            }
+        }
+
+        let subject_id = state.subjects.next()?;
+        match state.bridge.get_subject(subject_id.clone()).await {
+            Ok(subject) => state.current = Some((subject_id, 0, subject.sn)),
+            Err(e) => {
+                state.failed = true;
+                return Some((Err(io::Error::other(e.to_string())), state));
+            }
+        }
+    }
+}
+
+/// Streams the bundle of a subject, written by [`BundleWriter`], as the body of a response.
+///
+/// The events up to the sn of `subject` are read from the node in batches while the bundle is
+/// written, so memory does not grow with the number of events. An error reading from the node
+/// or writing the bundle ends the body with an error.
+pub fn export_bundle(
+    bridge: Arc<Bridge>,
+    subject: SubjectInfo,
+    signatures: SignaturesInfo,
+    governance_version: u64,
+) -> Body {
+    let (writer, reader) = tokio::io::duplex(BUNDLE_BUFFER);
+
+    let written = tokio::spawn(async move {
+        let mut bundle = BundleWriter::new(writer, &subject);
+        bundle.write_subject(&subject).await?;
+
+        for batch in 0..=subject.sn / EVENTS_BATCH {
+            let events = get_events_batch(&bridge, &subject.subject_id, batch)
+                .await
+                .map_err(|e| e.to_string())?;
+            for event in events.iter().filter(|x| x.sn <= subject.sn) {
+                bundle.write_event(event).await?;
+            }
+        }
+
+        bundle.write_signatures(&signatures).await?;
+        bundle.finish(&subject, governance_version).await
+    });
+
+    let failure = stream::once(async move {
+        match written.await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(Err(io::Error::other(e))),
+            Err(e) => Some(Err(io::Error::other(e))),
+        }
+    })
+    .filter_map(future::ready);
+
+    Body::from_stream(ReaderStream::new(reader).chain(failure))
+}
+
+fn ndjson_line(event: &EventInfo) -> Bytes {
+    let mut line = serde_json::to_vec(event).unwrap_or_default();
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+fn csv_record(event: &EventInfo) -> [String; 15] {
+    let mut record: [String; 15] = Default::default();
+    record[0] = event.subject_id.clone();
+    record[1] = event.sn.to_string();
+    record[2] = event.succes.to_string();
+    record[3] = format!("{:?}", EventType::from(&event.event_req));
+
+    match &event.event_req {
+        EventRequestInfo::Create(create) => {
+            record[4] = create.governance_id.clone();
+            record[5] = create.schema_id.clone();
+            record[6] = create.namespace.to_string();
+            record[7] = create.name.clone().unwrap_or_default();
+            record[8] = create.description.clone().unwrap_or_default();
+        }
+        EventRequestInfo::Fact(fact) => record[11] = fact.payload.to_string(),
+        EventRequestInfo::Transfer(transfer) => record[9] = transfer.new_owner.clone(),
+        EventRequestInfo::Confirm(confirm) => {
+            record[10] = confirm.name_old_owner.clone().unwrap_or_default()
+        }
+        EventRequestInfo::Reject(_) | EventRequestInfo::EOL(_) => {}
+    }
+
+    record[12] = event
+        .patch
+        .as_ref()
+        .map(|x| x.to_string())
+        .unwrap_or_default();
+    if let Some(error) = &event.error {
+        record[13] = error.evaluation.clone().unwrap_or_default();
+        record[14] = error.validation.clone().unwrap_or_default();
+    }
+    record
+}
+
+/// Joins the fields of a CSV record, quoting those with separators, quotes or line breaks.
+fn csv_line(fields: [String; 15]) -> Bytes {
+    let mut line = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+    line.push_str("\r\n");
+    Bytes::from(line)
+}
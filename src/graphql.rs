@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use async_graphql::{
+    Context, EmptyMutation, Object, Result, Schema, Subscription, async_stream::stream,
+    futures_util::Stream, http::ALL_WEBSOCKET_PROTOCOLS,
+};
+use async_graphql_axum::{GraphQL, GraphQLProtocol, GraphQLWebSocket};
+use axum::{
+    Extension, Router, extract::WebSocketUpgrade, middleware::from_fn_with_state,
+    response::Response, routing::get,
+};
+use kore_bridge::Bridge;
+
+use crate::{
+    error::Error,
+    middleware::{StreamLimit, StreamPermit, limit_stream},
+    pagination::{self, EVENTS_BATCH, PageCursor},
+    server::{POLL_INTERVAL, get_events_batch, get_events_page, get_governance},
+    shutdown::Shutdown,
+    wrappers::{
+        CursorEvents, EventInfo, EventType, GovsData, Member, RegisterDataSubj, SignaturesInfo,
+        SubjectInfo,
+    },
+};
+
+impl From<Error> for async_graphql::Error {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Kore(error) | Error::BadRequest(error) | Error::PreconditionFailed(error) => {
+                async_graphql::Error::new(error)
+            }
+        }
+    }
+}
+
+fn bridge<'a>(ctx: &Context<'a>) -> Result<&'a Arc<Bridge>> {
+    ctx.data::<Arc<Bridge>>()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The governances to which the node belongs.
+    async fn governances(
+        &self,
+        ctx: &Context<'_>,
+        active: Option<bool>,
+    ) -> Result<Vec<GovernanceNode>> {
+        match bridge(ctx)?.get_all_govs(active).await {
+            Ok(response) => Ok(response
+                .iter()
+                .map(|x| GovernanceNode(GovsData::from(x.clone())))
+                .collect()),
+            Err(e) => Err(Error::Kore(e.to_string()).into()),
+        }
+    }
+
+    /// A subject given its identifier.
+    async fn subject(&self, subject_id: String) -> SubjectNode {
+        SubjectNode {
+            subject_id,
+            register: None,
+        }
+    }
+}
+
+pub struct GovernanceNode(GovsData);
+
+#[Object]
+impl GovernanceNode {
+    async fn governance_id(&self) -> &str {
+        &self.0.governance_id
+    }
+
+    async fn active(&self) -> bool {
+        self.0.active
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    /// The members of the governance.
+    async fn members(&self, ctx: &Context<'_>) -> Result<Vec<Member>> {
+        let governance = get_governance(bridge(ctx)?, self.0.governance_id.clone()).await?;
+        Ok(governance.members)
+    }
+
+    /// The subjects of the governance, paginated by quantity and page.
+    async fn subjects(
+        &self,
+        ctx: &Context<'_>,
+        active: Option<bool>,
+        schema: Option<String>,
+        quantity: Option<u64>,
+        page: Option<u64>,
+    ) -> Result<Vec<SubjectNode>> {
+        let subjects: Vec<RegisterDataSubj> = match bridge(ctx)?
+            .get_all_subjs(self.0.governance_id.clone(), active, schema)
+            .await
+        {
+            Ok(response) => response
+                .iter()
+                .map(|x| RegisterDataSubj::from(x.clone()))
+                .collect(),
+            Err(e) => return Err(Error::Kore(e.to_string()).into()),
+        };
+
+        Ok(pagination::page(subjects, quantity, page)
+            .into_iter()
+            .map(|x| SubjectNode {
+                subject_id: x.subject_id.clone(),
+                register: Some(x),
+            })
+            .collect())
+    }
+}
+
+pub struct SubjectNode {
+    subject_id: String,
+    register: Option<RegisterDataSubj>,
+}
+
+#[Object]
+impl SubjectNode {
+    async fn subject_id(&self) -> &str {
+        &self.subject_id
+    }
+
+    /// The registry data of the subject, when listed from its governance.
+    async fn register(&self) -> Option<&RegisterDataSubj> {
+        self.register.as_ref()
+    }
+
+    /// The current state of the subject.
+    async fn state(&self, ctx: &Context<'_>) -> Result<SubjectInfo> {
+        match bridge(ctx)?.get_subject(self.subject_id.clone()).await {
+            Ok(response) => Ok(SubjectInfo::from(response)),
+            Err(e) => Err(Error::Kore(e.to_string()).into()),
+        }
+    }
+
+    /// The events of the subject, paginated with cursors keyed by sn.
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        cursor: Option<String>,
+        limit: Option<u64>,
+        event_type: Option<EventType>,
+        success: Option<bool>,
+    ) -> Result<CursorEvents> {
+        let (after, before) = match cursor.as_deref().map(PageCursor::decode).transpose()? {
+            Some(PageCursor::After(sn)) => (Some(sn), None),
+            Some(PageCursor::Before(sn)) => (None, Some(sn)),
+            None => (None, None),
+        };
+
+        let (events, next, prev) = get_events_page(
+            bridge(ctx)?,
+            &self.subject_id,
+            after,
+            before,
+            pagination::limit(limit),
+            event_type,
+            success,
+        )
+        .await?;
+
+        Ok(CursorEvents {
+            events,
+            next: next.map(|x| x.encode()),
+            prev: prev.map(|x| x.encode()),
+        })
+    }
+
+    /// The signatures of the last event of the subject.
+    async fn signatures(&self, ctx: &Context<'_>) -> Result<SignaturesInfo> {
+        match bridge(ctx)?.get_signatures(self.subject_id.clone()).await {
+            Ok(response) => Ok(SignaturesInfo::from(response)),
+            Err(e) => Err(Error::Kore(e.to_string()).into()),
+        }
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    /// The new events of a subject, starting after the given sn or after the current one.
+    async fn events(
+        &self,
+        ctx: &Context<'_>,
+        subject_id: String,
+        after_sn: Option<u64>,
+    ) -> Result<impl Stream<Item = Result<EventInfo>>> {
+        let bridge = bridge(ctx)?.clone();
+
+        let mut last_sn = match after_sn {
+            Some(sn) => Some(sn),
+            None => match bridge.get_subject(subject_id.clone()).await {
+                Ok(subject) => Some(subject.sn),
+                Err(e) => return Err(Error::Kore(e.to_string()).into()),
+            },
+        };
+
+        Ok(stream! {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            'watch: loop {
+                interval.tick().await;
+
+                let sn = match bridge.get_subject(subject_id.clone()).await {
+                    Ok(subject) => subject.sn,
+                    Err(e) => {
+                        yield Err(Error::Kore(e.to_string()).into());
+                        break;
+                    }
+                };
+
+                let from = last_sn.map_or(0, |x| x + 1);
+                for batch in from / EVENTS_BATCH..=sn / EVENTS_BATCH {
+                    let events = match get_events_batch(&bridge, &subject_id, batch).await {
+                        Ok(events) => events,
+                        Err(e) => {
+                            yield Err(e.into());
+                            break 'watch;
+                        }
+                    };
+
+                    for event in events.into_iter().filter(|x| (from..=sn).contains(&x.sn)) {
+                        last_sn = Some(event.sn);
+                        yield Ok(event);
+                    }
+                }
+            }
+        })
+    }
+}
+
+pub type KoreSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+/// Builds the GraphQL routes, with queries at `/graphql` and subscriptions over
+/// WebSocket at `/graphql/ws`, each socket counting against `streams` and closing when the
+/// shutdown starts.
+pub fn build_graphql_routes(
+    bridge: Arc<Bridge>,
+    streams: StreamLimit,
+    shutdown: Shutdown,
+) -> Router {
+    let schema: KoreSchema = Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(bridge)
+        .finish();
+
+    Router::new()
+        .route_service("/graphql", GraphQL::new(schema.clone()))
+        .route(
+            "/graphql/ws",
+            get(subscriptions)
+                .layer(Extension(schema))
+                .layer(Extension(shutdown))
+                .layer(from_fn_with_state(streams, limit_stream)),
+        )
+}
+
+async fn subscriptions(
+    Extension(schema): Extension<KoreSchema>,
+    Extension(permit): Extension<StreamPermit>,
+    Extension(shutdown): Extension<Shutdown>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| async move {
+            // The stream permit is held for as long as the socket is open.
+            let _permit = permit;
+            tokio::select! {
+                _ = GraphQLWebSocket::new(socket, schema, protocol).serve() => {}
+                _ = shutdown.started() => {}
+            }
+        })
+}
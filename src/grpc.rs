@@ -0,0 +1,719 @@
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::Router;
+use kore_bridge::{Bridge, model::BridgeSignedEventRequest};
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, service::Routes, transport::Server};
+
+use crate::{
+    cache::ResponseCache,
+    error::Error,
+    middleware::StreamLimit,
+    pagination::{EVENTS_BATCH, MAX_LIMIT, PageCursor},
+    server::{POLL_INTERVAL, get_events_batch, get_events_page},
+    shutdown::Shutdown,
+    validation::validate_event_request,
+    wrappers,
+};
+
+pub mod proto {
+    tonic::include_proto!("kore.v1");
+}
+
+use proto::{
+    event_request_info, kore_server::Kore, kore_server::KoreServer, protocols_signatures_info,
+};
+
+/// Number of messages buffered per stream before waiting for the client.
+const STREAM_BUFFER: usize = 16;
+
+impl From<Error> for Status {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Kore(error) => Status::internal(error),
+            Error::BadRequest(error) => Status::invalid_argument(error),
+            Error::PreconditionFailed(error) => Status::failed_precondition(error),
+        }
+    }
+}
+
+fn kore(e: impl ToString) -> Status {
+    Status::internal(e.to_string())
+}
+
+fn message(value: String) -> Response<proto::Message> {
+    Response::new(proto::Message { value })
+}
+
+pub struct KoreService {
+    bridge: Arc<Bridge>,
+    cache: Arc<ResponseCache>,
+    streams: StreamLimit,
+    shutdown: Shutdown,
+}
+
+#[tonic::async_trait]
+impl Kore for KoreService {
+    type GetEventsStream = ReceiverStream<Result<proto::EventInfo, Status>>;
+    type WatchEventsStream = ReceiverStream<Result<proto::EventInfo, Status>>;
+
+    async fn send_event_request(
+        &self,
+        request: Request<proto::SignedEventRequest>,
+    ) -> Result<Response<proto::RequestData>, Status> {
+        let request = signed_event_request(request.into_inner())?;
+        validate_event_request(&self.bridge, &self.cache, &request).await?;
+
+        let event_request = request.request.clone();
+        match self.bridge.send_event_request(request).await {
+            Ok(response) => {
+                self.cache.invalidate_request(&event_request).await;
+                Ok(Response::new(wrappers::RequestData::from(response).into()))
+            }
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_request_state(
+        &self,
+        request: Request<proto::RequestId>,
+    ) -> Result<Response<proto::RequestInfo>, Status> {
+        let request_id = request.into_inner().request_id;
+        match self.bridge.get_request_state(request_id).await {
+            Ok(response) => Ok(Response::new(wrappers::RequestInfo::from(response).into())),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_approval(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::ApproveInfo>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.get_approval(subject_id).await {
+            Ok(response) => Ok(Response::new(wrappers::ApproveInfo::from(response).into())),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn patch_approval(
+        &self,
+        request: Request<proto::ApprovalResponse>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let request = request.into_inner();
+        match self
+            .bridge
+            .patch_approve(request.subject_id, request.response)
+            .await
+        {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn put_auth(
+        &self,
+        request: Request<proto::AuthRequest>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let request = request.into_inner();
+        match self
+            .bridge
+            .put_auth_subject(request.subject_id, request.witnesses)
+            .await
+        {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_all_auth_subjects(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Identifiers>, Status> {
+        match self.bridge.get_all_auth_subjects().await {
+            Ok(identifiers) => Ok(Response::new(proto::Identifiers { identifiers })),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_witnesses_subject(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::Identifiers>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.get_witnesses_subject(subject_id).await {
+            Ok(identifiers) => Ok(Response::new(proto::Identifiers { identifiers })),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn delete_auth_subject(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.delete_auth_subject(subject_id).await {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn update_subject(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.update_subject(subject_id).await {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn check_transfer(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.check_transfer(subject_id).await {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn manual_distribution(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::Message>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.manual_distribution(subject_id).await {
+            Ok(response) => Ok(message(response)),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_all_govs(
+        &self,
+        request: Request<proto::GovQuery>,
+    ) -> Result<Response<proto::GovsDataList>, Status> {
+        let active = request.into_inner().active;
+        match self.bridge.get_all_govs(active).await {
+            Ok(response) => Ok(Response::new(proto::GovsDataList {
+                governances: response
+                    .iter()
+                    .map(|x| wrappers::GovsData::from(x.clone()).into())
+                    .collect(),
+            })),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_all_subjects(
+        &self,
+        request: Request<proto::SubjectQuery>,
+    ) -> Result<Response<proto::RegisterDataSubjList>, Status> {
+        let query = request.into_inner();
+        match self
+            .bridge
+            .get_all_subjs(query.governance_id, query.active, query.schema)
+            .await
+        {
+            Ok(response) => Ok(Response::new(proto::RegisterDataSubjList {
+                subjects: response
+                    .iter()
+                    .map(|x| wrappers::RegisterDataSubj::from(x.clone()).into())
+                    .collect(),
+            })),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_state(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::SubjectInfo>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.get_subject(subject_id).await {
+            Ok(response) => Ok(Response::new(wrappers::SubjectInfo::from(response).into())),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_event_sn(
+        &self,
+        request: Request<proto::EventSnQuery>,
+    ) -> Result<Response<proto::EventInfo>, Status> {
+        let query = request.into_inner();
+        match self.bridge.get_event_sn(query.subject_id, query.sn).await {
+            Ok(response) => Ok(Response::new(wrappers::EventInfo::from(response).into())),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_events(
+        &self,
+        request: Request<proto::EventsQuery>,
+    ) -> Result<Response<Self::GetEventsStream>, Status> {
+        let query = request.into_inner();
+        let bridge = self.bridge.clone();
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let mut after = query.after_sn;
+            let mut remaining = query.limit;
+            while remaining != Some(0) {
+                let limit = remaining.map_or(MAX_LIMIT, |x| x.min(MAX_LIMIT));
+                let page = get_events_page(
+                    &bridge,
+                    &query.subject_id,
+                    after,
+                    None,
+                    limit,
+                    None,
+                    query.success,
+                )
+                .await;
+
+                let (events, next) = match page {
+                    Ok((events, next, _)) => (events, next),
+                    Err(e) => {
+                        let _ = sender.send(Err(e.into())).await;
+                        return;
+                    }
+                };
+
+                for event in events {
+                    remaining = remaining.map(|x| x - 1);
+                    if sender.send(Ok(event.into())).await.is_err() {
+                        return;
+                    }
+                }
+
+                match next {
+                    Some(PageCursor::After(sn)) => after = Some(sn),
+                    _ => return,
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn watch_events(
+        &self,
+        request: Request<proto::WatchQuery>,
+    ) -> Result<Response<Self::WatchEventsStream>, Status> {
+        let query = request.into_inner();
+        let bridge = self.bridge.clone();
+        let shutdown = self.shutdown.clone();
+        let Some(permit) = self.streams.try_acquire() else {
+            return Err(Status::unavailable("The server has too many open streams"));
+        };
+
+        let mut last_sn = match query.after_sn {
+            Some(sn) => sn,
+            None => match bridge.get_subject(query.subject_id.clone()).await {
+                Ok(subject) => subject.sn,
+                Err(e) => return Err(kore(e)),
+            },
+        };
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            // The stream permit is held until the client goes away or the shutdown starts.
+            let _permit = permit;
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.started() => return,
+                }
+
+                let sn = match bridge.get_subject(query.subject_id.clone()).await {
+                    Ok(subject) => subject.sn,
+                    Err(e) => {
+                        let _ = sender.send(Err(kore(e))).await;
+                        return;
+                    }
+                };
+
+                let from = last_sn + 1;
+                for batch in from / EVENTS_BATCH..=sn / EVENTS_BATCH {
+                    let events = match get_events_batch(&bridge, &query.subject_id, batch).await {
+                        Ok(events) => events,
+                        Err(e) => {
+                            let _ = sender.send(Err(e.into())).await;
+                            return;
+                        }
+                    };
+
+                    for event in events.into_iter().filter(|x| (from..=sn).contains(&x.sn)) {
+                        last_sn = event.sn;
+                        if sender.send(Ok(event.into())).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn get_signatures(
+        &self,
+        request: Request<proto::SubjectId>,
+    ) -> Result<Response<proto::SignaturesInfo>, Status> {
+        let subject_id = request.into_inner().subject_id;
+        match self.bridge.get_signatures(subject_id).await {
+            Ok(response) => Ok(Response::new(
+                wrappers::SignaturesInfo::from(response).into(),
+            )),
+            Err(e) => Err(kore(e)),
+        }
+    }
+
+    async fn get_controller_id(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Message>, Status> {
+        Ok(message(self.bridge.controller_id()))
+    }
+
+    async fn get_peer_id(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Message>, Status> {
+        Ok(message(self.bridge.peer_id()))
+    }
+
+    async fn get_pending_transfers(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::TransferSubjects>, Status> {
+        match self.bridge.get_pending_transfers().await {
+            Ok(response) => Ok(Response::new(proto::TransferSubjects {
+                transfers: response
+                    .iter()
+                    .map(|x| wrappers::TransferSubject::from(x.clone()).into())
+                    .collect(),
+            })),
+            Err(e) => Err(kore(e)),
+        }
+    }
+}
+
+/// Builds the gRPC routes, to be served on the same port as the HTTP API.
+///
+/// gRPC methods live under `/kore.v1.Kore/`, so requests with the `application/grpc`
+/// content type never collide with the routes of the HTTP API. Each watch counts against
+/// `streams` and ends when the shutdown starts.
+pub fn build_grpc_routes(
+    bridge: Arc<Bridge>,
+    cache: Arc<ResponseCache>,
+    streams: StreamLimit,
+    shutdown: Shutdown,
+) -> Router {
+    let service = KoreService {
+        bridge,
+        cache,
+        streams,
+        shutdown,
+    };
+    Routes::new(KoreServer::new(service)).into_axum_router()
+}
+
+/// Serves the gRPC service on its own address until the listeners close after the shutdown
+/// starts, letting the calls in progress finish within `shutdown_timeout`.
+pub async fn serve_grpc(
+    bridge: Arc<Bridge>,
+    cache: Arc<ResponseCache>,
+    streams: StreamLimit,
+    address: String,
+    shutdown: Shutdown,
+    shutdown_timeout: Duration,
+) -> Result<(), String> {
+    let address: SocketAddr = address
+        .parse()
+        .map_err(|e| format!("Invalid gRPC address {}: {}", address, e))?;
+
+    tracing::info!("Serving gRPC on {}", address);
+    let service = KoreService {
+        bridge,
+        cache,
+        streams,
+        shutdown: shutdown.clone(),
+    };
+    let graceful = shutdown.clone();
+    let server = Server::builder()
+        .add_service(KoreServer::new(service))
+        .serve_with_shutdown(address, async move { graceful.closing().await });
+
+    shutdown
+        .drain(
+            async move { server.await.map_err(io::Error::other) },
+            shutdown_timeout,
+        )
+        .await
+        .map_err(|e| format!("Error serving gRPC on {}: {}", address, e))
+}
+
+impl From<wrappers::RequestData> for proto::RequestData {
+    fn from(value: wrappers::RequestData) -> Self {
+        Self {
+            request_id: value.request_id,
+            subject_id: value.subject_id,
+        }
+    }
+}
+
+impl From<wrappers::RequestInfo> for proto::RequestInfo {
+    fn from(value: wrappers::RequestInfo) -> Self {
+        Self {
+            status: value.status,
+            version: value.version,
+            error: value.error,
+        }
+    }
+}
+
+impl From<wrappers::ApproveInfo> for proto::ApproveInfo {
+    fn from(value: wrappers::ApproveInfo) -> Self {
+        let request = value.request;
+        Self {
+            state: value.state,
+            request: Some(proto::ApprovalReqInfo {
+                event_request: Some(proto::SignedFactInfo {
+                    content: Some(proto::FactInfo {
+                        payload: request.event_request.content.payload.to_string(),
+                        subject_id: request.event_request.content.subject_id,
+                    }),
+                    signature: Some(request.event_request.signature.into()),
+                }),
+                sn: request.sn,
+                gov_version: request.gov_version,
+                patch: request.patch.to_string(),
+                state_hash: request.state_hash,
+                hash_prev_event: request.hash_prev_event,
+                subject_id: request.subject_id,
+            }),
+        }
+    }
+}
+
+impl From<wrappers::GovsData> for proto::GovsData {
+    fn from(value: wrappers::GovsData) -> Self {
+        Self {
+            governance_id: value.governance_id,
+            active: value.active,
+            name: value.name,
+            description: value.description,
+        }
+    }
+}
+
+impl From<wrappers::RegisterDataSubj> for proto::RegisterDataSubj {
+    fn from(value: wrappers::RegisterDataSubj) -> Self {
+        Self {
+            subject_id: value.subject_id,
+            schema: value.schema,
+            active: value.active,
+            name: value.name,
+            description: value.description,
+        }
+    }
+}
+
+impl From<wrappers::SubjectInfo> for proto::SubjectInfo {
+    fn from(value: wrappers::SubjectInfo) -> Self {
+        Self {
+            subject_id: value.subject_id,
+            governance_id: value.governance_id,
+            genesis_gov_version: value.genesis_gov_version,
+            namespace: value.namespace,
+            schema_id: value.schema_id,
+            owner: value.owner,
+            creator: value.creator,
+            active: value.active,
+            sn: value.sn,
+            properties: value.properties.to_string(),
+            new_owner: value.new_owner,
+            name: value.name,
+            description: value.description,
+        }
+    }
+}
+
+impl From<wrappers::EventInfo> for proto::EventInfo {
+    fn from(value: wrappers::EventInfo) -> Self {
+        Self {
+            subject_id: value.subject_id,
+            sn: value.sn,
+            patch: value.patch.map(|x| x.to_string()),
+            error: value.error.map(|x| proto::ProtocolsError {
+                evaluation: x.evaluation,
+                validation: x.validation,
+            }),
+            event_req: Some(value.event_req.into()),
+            succes: value.succes,
+        }
+    }
+}
+
+/// Builds the signed event request the HTTP API accepts as JSON from its proto form.
+fn signed_event_request(
+    value: proto::SignedEventRequest,
+) -> Result<BridgeSignedEventRequest, Status> {
+    let Some(proto::EventRequestInfo {
+        request: Some(request),
+    }) = value.request
+    else {
+        return Err(Status::invalid_argument("The event request is missing"));
+    };
+
+    let request = match request {
+        event_request_info::Request::Create(x) => json!({
+            "Create": {
+                "governance_id": x.governance_id,
+                "schema_id": x.schema_id,
+                "namespace": x.namespace,
+                "name": x.name,
+                "description": x.description,
+            }
+        }),
+        event_request_info::Request::Fact(x) => {
+            let payload: Value = serde_json::from_str(&x.payload)
+                .map_err(|e| Status::invalid_argument(format!("Invalid fact payload: {}", e)))?;
+            json!({ "Fact": { "subject_id": x.subject_id, "payload": payload } })
+        }
+        event_request_info::Request::Transfer(x) => json!({
+            "Transfer": { "subject_id": x.subject_id, "new_owner": x.new_owner }
+        }),
+        event_request_info::Request::Confirm(x) => json!({
+            "Confirm": { "subject_id": x.subject_id, "name_old_owner": x.name_old_owner }
+        }),
+        event_request_info::Request::Reject(x) => json!({
+            "Reject": { "subject_id": x.subject_id }
+        }),
+        event_request_info::Request::Eol(x) => json!({ "EOL": { "subject_id": x.subject_id } }),
+    };
+
+    let signature = value.signature.map(|x| {
+        json!({
+            "signer": x.signer,
+            "timestamp": x.timestamp,
+            "content_hash": x.content_hash,
+            "value": x.value,
+        })
+    });
+
+    serde_json::from_value(json!({ "request": request, "signature": signature }))
+        .map_err(|e| Status::invalid_argument(format!("Invalid event request: {}", e)))
+}
+
+impl From<wrappers::EventRequestInfo> for proto::EventRequestInfo {
+    fn from(value: wrappers::EventRequestInfo) -> Self {
+        let request = match value {
+            wrappers::EventRequestInfo::Create(x) => {
+                event_request_info::Request::Create(proto::CreateRequestInfo {
+                    governance_id: x.governance_id,
+                    schema_id: x.schema_id,
+                    namespace: x.namespace.to_string(),
+                    name: x.name,
+                    description: x.description,
+                })
+            }
+            wrappers::EventRequestInfo::Fact(x) => {
+                event_request_info::Request::Fact(proto::FactRequestInfo {
+                    subject_id: x.subject_id,
+                    payload: x.payload.to_string(),
+                })
+            }
+            wrappers::EventRequestInfo::Transfer(x) => {
+                event_request_info::Request::Transfer(proto::TransferRequestInfo {
+                    subject_id: x.subject_id,
+                    new_owner: x.new_owner,
+                })
+            }
+            wrappers::EventRequestInfo::Confirm(x) => {
+                event_request_info::Request::Confirm(proto::ConfirmRequestInfo {
+                    subject_id: x.subject_id,
+                    name_old_owner: x.name_old_owner,
+                })
+            }
+            wrappers::EventRequestInfo::Reject(x) => {
+                event_request_info::Request::Reject(proto::RejectRequestInfo {
+                    subject_id: x.subject_id,
+                })
+            }
+            wrappers::EventRequestInfo::EOL(x) => {
+                event_request_info::Request::Eol(proto::EolRequestInfo {
+                    subject_id: x.subject_id,
+                })
+            }
+        };
+
+        Self {
+            request: Some(request),
+        }
+    }
+}
+
+impl From<wrappers::SignaturesInfo> for proto::SignaturesInfo {
+    fn from(value: wrappers::SignaturesInfo) -> Self {
+        Self {
+            subject_id: value.subject_id,
+            sn: value.sn,
+            signatures_eval: value.signatures_eval.map(signatures),
+            signatures_appr: value.signatures_appr.map(signatures),
+            signatures_vali: Some(signatures(value.signatures_vali)),
+        }
+    }
+}
+
+fn signatures(
+    set: impl IntoIterator<Item = wrappers::ProtocolsSignaturesInfo>,
+) -> proto::ProtocolsSignatures {
+    let signatures = set
+        .into_iter()
+        .map(|x| {
+            let info = match x {
+                wrappers::ProtocolsSignaturesInfo::Signature(signature) => {
+                    protocols_signatures_info::Info::Signature(signature.into())
+                }
+                wrappers::ProtocolsSignaturesInfo::TimeOut(timeout) => {
+                    protocols_signatures_info::Info::TimeOut(proto::TimeOutResponseInfo {
+                        who: timeout.who,
+                        re_trys: timeout.re_trys,
+                        timestamp: timeout.timestamp,
+                    })
+                }
+            };
+            proto::ProtocolsSignaturesInfo { info: Some(info) }
+        })
+        .collect();
+
+    proto::ProtocolsSignatures { signatures }
+}
+
+impl From<wrappers::SignatureInfo> for proto::SignatureInfo {
+    fn from(value: wrappers::SignatureInfo) -> Self {
+        Self {
+            signer: value.signer,
+            timestamp: value.timestamp,
+            content_hash: value.content_hash,
+            value: value.value,
+        }
+    }
+}
+
+impl From<wrappers::TransferSubject> for proto::TransferSubject {
+    fn from(value: wrappers::TransferSubject) -> Self {
+        Self {
+            subject_id: value.subject_id,
+            new_owner: value.new_owner,
+            actual_owner: value.actual_owner,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+pub mod bundle;
+pub mod cache;
+mod doc;
+pub mod enviroment;
+pub mod error;
+mod etag;
+mod export;
+#[cfg(feature = "graphql")]
+mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod listener;
+pub mod middleware;
+mod pagination;
+mod quorum;
+pub mod server;
+pub mod shutdown;
+mod state;
+mod validation;
+pub mod wrappers;
+mod ws;
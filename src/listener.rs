@@ -0,0 +1,536 @@
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    http::{
+        HeaderMap, StatusCode, Uri, header,
+        uri::{Authority, PathAndQuery, Scheme},
+    },
+    response::Redirect,
+};
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::{net::TcpListener, task::JoinSet};
+use tracing::{info, warn};
+
+use crate::{
+    enviroment::{
+        build_address_http, build_address_https, build_https_cert, build_https_private_key,
+        build_https_public_url, build_listeners,
+    },
+    shutdown::Shutdown,
+};
+
+/// Transport on which a listener accepts connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    Http(SocketAddr),
+    Https(SocketAddr),
+    Unix(PathBuf),
+}
+
+/// What a listener does with the requests it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Serves the API.
+    Api,
+    /// Redirects every request to the HTTPS API.
+    Redirect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listener {
+    pub transport: Transport,
+    pub mode: Mode,
+}
+
+impl FromStr for Listener {
+    type Err = String;
+
+    /// Parses a listener such as `http://0.0.0.0:3000`, `https://[::]:3443` or
+    /// `unix:///run/kore-http.sock`, optionally followed by `=api` or `=redirect`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let (value, mode) = match value.rsplit_once('=') {
+            Some((value, "api")) => (value, Mode::Api),
+            Some((value, "redirect")) => (value, Mode::Redirect),
+            _ => (value, Mode::Api),
+        };
+
+        let Some((scheme, address)) = value.split_once("://") else {
+            return Err(format!(
+                "Invalid listener {}, expected <scheme>://<address>",
+                value
+            ));
+        };
+        let socket = || {
+            SocketAddr::from_str(address)
+                .map_err(|e| format!("Invalid address of the listener {}: {}", value, e))
+        };
+
+        let transport = match scheme {
+            "http" => Transport::Http(socket()?),
+            "https" => Transport::Https(socket()?),
+            "unix" if cfg!(unix) => Transport::Unix(PathBuf::from(address)),
+            "unix" => return Err("Unix socket listeners are only supported on Unix".to_owned()),
+            _ => {
+                return Err(format!(
+                    "Invalid scheme of the listener {}, expected http, https or unix",
+                    value
+                ));
+            }
+        };
+
+        if mode == Mode::Redirect && matches!(transport, Transport::Https(_)) {
+            return Err(format!("The HTTPS listener {} can not redirect", value));
+        }
+
+        Ok(Self { transport, mode })
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.transport {
+            Transport::Http(address) => write!(f, "http://{}", address)?,
+            Transport::Https(address) => write!(f, "https://{}", address)?,
+            Transport::Unix(path) => write!(f, "unix://{}", path.display())?,
+        }
+        match self.mode {
+            Mode::Api => Ok(()),
+            Mode::Redirect => write!(f, "=redirect"),
+        }
+    }
+}
+
+/// Listeners of the server.
+///
+/// `KORE_HTTP_LISTENERS` is a comma separated list of listeners. When it is not set the API is
+/// served over HTTP on `KORE_HTTP_ADDRESS` or, if `KORE_HTTPS_ADDRESS` is set, over HTTPS on it,
+/// with the HTTP address redirecting to it.
+pub fn listeners() -> Result<Vec<Listener>, String> {
+    let listeners = build_listeners();
+    if !listeners.trim().is_empty() {
+        return listeners
+            .split(',')
+            .filter(|x| !x.trim().is_empty())
+            .map(Listener::from_str)
+            .collect();
+    }
+
+    let https = build_address_https();
+    if https.is_empty() {
+        Ok(vec![format!("http://{}", build_address_http()).parse()?])
+    } else {
+        Ok(vec![
+            format!("http://{}=redirect", build_address_http()).parse()?,
+            format!("https://{}", https).parse()?,
+        ])
+    }
+}
+
+/// Where redirecting listeners send their requests.
+#[derive(Debug, Clone)]
+enum RedirectTarget {
+    /// The public HTTPS URL of the API, `KORE_HTTPS_PUBLIC_URL`, for nodes behind a proxy.
+    Url(Uri),
+    /// The host of the request at the port of the HTTPS listener.
+    Port(u16),
+}
+
+impl RedirectTarget {
+    fn build(listeners: &[Listener]) -> Result<Option<Self>, String> {
+        let public_url = build_https_public_url();
+        if !public_url.is_empty() {
+            let url = Uri::from_str(&public_url)
+                .map_err(|e| format!("Invalid public HTTPS URL {}: {}", public_url, e))?;
+            if url.scheme() != Some(&Scheme::HTTPS) || url.authority().is_none() {
+                return Err(format!(
+                    "Invalid public HTTPS URL {}, expected https://<host>[:<port>][/<path>]",
+                    public_url
+                ));
+            }
+            return Ok(Some(RedirectTarget::Url(url)));
+        }
+
+        Ok(listeners.iter().find_map(|x| match x.transport {
+            Transport::Https(address) if x.mode == Mode::Api => {
+                Some(RedirectTarget::Port(address.port()))
+            }
+            _ => None,
+        }))
+    }
+
+    /// The HTTPS URI to which a request for `uri` on `host` is redirected.
+    fn redirect(&self, host: Option<&str>, uri: &Uri) -> Result<Uri, String> {
+        let path_and_query = uri
+            .path_and_query()
+            .map(PathAndQuery::as_str)
+            .unwrap_or("/");
+
+        match self {
+            RedirectTarget::Url(url) => {
+                let base = url.path().trim_end_matches('/');
+                Uri::builder()
+                    .scheme(Scheme::HTTPS)
+                    .authority(url.authority().map(Authority::as_str).unwrap_or_default())
+                    .path_and_query(format!("{}{}", base, path_and_query))
+                    .build()
+                    .map_err(|e| e.to_string())
+            }
+            RedirectTarget::Port(port) => {
+                let authority = match host {
+                    Some(host) => Authority::from_str(host).map_err(|e| e.to_string())?,
+                    None => uri
+                        .authority()
+                        .cloned()
+                        .ok_or("The request has no host".to_owned())?,
+                };
+
+                // IPv6 hosts keep their brackets once the port is replaced.
+                let host = authority.host();
+                let host = if host.contains(':') && !host.starts_with('[') {
+                    format!("[{}]", host)
+                } else {
+                    host.to_owned()
+                };
+                let authority = if *port == 443 {
+                    host
+                } else {
+                    format!("{}:{}", host, port)
+                };
+
+                Uri::builder()
+                    .scheme(Scheme::HTTPS)
+                    .authority(authority)
+                    .path_and_query(path_and_query)
+                    .build()
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+fn redirect_routes(target: RedirectTarget) -> Router {
+    Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        let host = headers.get(header::HOST).and_then(|x| x.to_str().ok());
+        match target.redirect(host, &uri) {
+            Ok(uri) => Ok(Redirect::permanent(&uri.to_string())),
+            Err(error) => {
+                warn!(%error, "failed to convert URI to HTTPS");
+                Err(StatusCode::BAD_REQUEST)
+            }
+        }
+    })
+}
+
+/// A listener bound to its address, ready to serve.
+enum Bound {
+    Http(TcpListener),
+    Https(std::net::TcpListener, RustlsConfig),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// Serves `routes` or redirects to HTTPS on every listener, until the listeners close after
+/// the shutdown starts and they drain their connections, within `shutdown_timeout`.
+///
+/// Every listener is bound before any of them starts serving, so an invalid configuration is
+/// reported before the server takes requests.
+pub async fn serve(
+    listeners: Vec<Listener>,
+    routes: Router,
+    shutdown: Shutdown,
+    shutdown_timeout: Duration,
+) -> Result<(), String> {
+    if !listeners.iter().any(|x| x.mode == Mode::Api) {
+        return Err("No listener serves the API".to_owned());
+    }
+
+    let target = RedirectTarget::build(&listeners)?;
+    let redirect = if listeners.iter().any(|x| x.mode == Mode::Redirect) {
+        match target {
+            Some(target) => Some(redirect_routes(target)),
+            None => {
+                return Err(
+                    "Redirecting listeners need an HTTPS listener or a public HTTPS URL".to_owned(),
+                );
+            }
+        }
+    } else {
+        None
+    };
+
+    let tls = if listeners
+        .iter()
+        .any(|x| matches!(x.transport, Transport::Https(_)))
+    {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        Some(
+            RustlsConfig::from_pem_file(
+                PathBuf::from(&build_https_cert()),
+                PathBuf::from(&build_https_private_key()),
+            )
+            .await
+            .map_err(|e| format!("Error loading the HTTPS certificate: {}", e))?,
+        )
+    } else {
+        None
+    };
+
+    let mut bound = Vec::with_capacity(listeners.len());
+    for listener in listeners {
+        let name = listener.to_string();
+        let bind_error = |e: io::Error| format!("Error binding {}: {}", name, e);
+
+        let socket = match listener.transport {
+            Transport::Http(address) => {
+                Bound::Http(TcpListener::bind(address).await.map_err(bind_error)?)
+            }
+            Transport::Https(address) => {
+                let tcp = std::net::TcpListener::bind(address).map_err(bind_error)?;
+                tcp.set_nonblocking(true).map_err(bind_error)?;
+                let tls = tls
+                    .clone()
+                    .ok_or("The HTTPS certificate is not loaded".to_owned())?;
+                Bound::Https(tcp, tls)
+            }
+            #[cfg(unix)]
+            Transport::Unix(path) => Bound::Unix(bind_unix(&path).map_err(bind_error)?, path),
+            #[cfg(not(unix))]
+            Transport::Unix(_) => {
+                return Err("Unix socket listeners are only supported on Unix".to_owned());
+            }
+        };
+        info!("Listening on {}", name);
+        bound.push((name, listener.mode, socket));
+    }
+
+    let mut servers = JoinSet::new();
+    for (name, mode, socket) in bound {
+        let app = match mode {
+            Mode::Api => routes.clone(),
+            Mode::Redirect => redirect.clone().unwrap_or_default(),
+        };
+        let shutdown = shutdown.clone();
+
+        match socket {
+            Bound::Http(tcp) => {
+                servers.spawn(async move {
+                    let graceful = shutdown.clone();
+                    let result = shutdown
+                        .drain(
+                            axum::serve(
+                                tcp,
+                                app.into_make_service_with_connect_info::<SocketAddr>(),
+                            )
+                            .with_graceful_shutdown(async move { graceful.closing().await }),
+                            shutdown_timeout,
+                        )
+                        .await;
+                    (name, result)
+                });
+            }
+            Bound::Https(tcp, tls) => {
+                let handle = Handle::new();
+                let server = axum_server::from_tcp_rustls(tcp, tls).handle(handle.clone());
+                servers.spawn(async move {
+                    tokio::spawn(async move {
+                        shutdown.closing().await;
+                        handle.graceful_shutdown(Some(shutdown_timeout));
+                    });
+                    let result = server
+                        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                        .await;
+                    (name, result)
+                });
+            }
+            #[cfg(unix)]
+            Bound::Unix(unix, path) => {
+                servers.spawn(async move {
+                    let graceful = shutdown.clone();
+                    let result = shutdown
+                        .drain(
+                            axum::serve(unix, app.into_make_service())
+                                .with_graceful_shutdown(async move { graceful.closing().await }),
+                            shutdown_timeout,
+                        )
+                        .await;
+                    let _ = std::fs::remove_file(&path);
+                    (name, result)
+                });
+            }
+        }
+    }
+
+    let mut error = None;
+    while let Some(result) = servers.join_next().await {
+        match result {
+            Ok((name, Ok(()))) => info!("Stopped listening on {}", name),
+            Ok((name, Err(e))) => {
+                error = Some(format!("Error serving {}: {}", name, e));
+                shutdown.start();
+            }
+            Err(e) => {
+                error = Some(e.to_string());
+                shutdown.start();
+            }
+        }
+    }
+
+    error.map_or(Ok(()), Err)
+}
+
+/// Binds a Unix socket, replacing the one left behind by a previous run, if any.
+#[cfg(unix)]
+fn bind_unix(path: &Path) -> io::Result<UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    if std::fs::symlink_metadata(path).is_ok_and(|x| x.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(target: &RedirectTarget, host: Option<&str>, uri: &str) -> String {
+        target
+            .redirect(host, &Uri::from_str(uri).unwrap())
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn parses_listeners() {
+        assert_eq!(
+            Listener::from_str("http://0.0.0.0:3000").unwrap(),
+            Listener {
+                transport: Transport::Http(SocketAddr::from(([0, 0, 0, 0], 3000))),
+                mode: Mode::Api,
+            }
+        );
+        assert_eq!(
+            Listener::from_str(" https://[::]:443=api ").unwrap(),
+            Listener {
+                transport: Transport::Https(SocketAddr::from_str("[::]:443").unwrap()),
+                mode: Mode::Api,
+            }
+        );
+        assert_eq!(
+            Listener::from_str("http://[::1]:8080=redirect").unwrap(),
+            Listener {
+                transport: Transport::Http(SocketAddr::from_str("[::1]:8080").unwrap()),
+                mode: Mode::Redirect,
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parses_unix_listeners() {
+        assert_eq!(
+            Listener::from_str("unix:///run/kore-http.sock").unwrap(),
+            Listener {
+                transport: Transport::Unix(PathBuf::from("/run/kore-http.sock")),
+                mode: Mode::Api,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_listeners() {
+        assert!(Listener::from_str("0.0.0.0:3000").is_err());
+        assert!(Listener::from_str("ftp://0.0.0.0:21").is_err());
+        assert!(Listener::from_str("http://localhost:3000").is_err());
+        assert!(Listener::from_str("http://::1:3000").is_err());
+        assert!(Listener::from_str("https://0.0.0.0:443=redirect").is_err());
+    }
+
+    #[test]
+    fn listeners_are_displayed_as_parsed() {
+        for listener in [
+            "http://0.0.0.0:3000",
+            "https://[::1]:3443",
+            "http://[::]:80=redirect",
+        ] {
+            assert_eq!(Listener::from_str(listener).unwrap().to_string(), listener);
+        }
+    }
+
+    #[test]
+    fn redirects_to_the_https_port() {
+        let target = RedirectTarget::Port(3443);
+        assert_eq!(
+            redirect(
+                &target,
+                Some("example.com:3000"),
+                "/v1/subjects?active=true"
+            ),
+            "https://example.com:3443/v1/subjects?active=true"
+        );
+        assert_eq!(
+            redirect(&target, Some("example.com"), "/"),
+            "https://example.com:3443/"
+        );
+        assert_eq!(
+            redirect(&target, None, "http://example.com:3000/doc"),
+            "https://example.com:3443/doc"
+        );
+        assert!(target.redirect(None, &Uri::from_static("/doc")).is_err());
+    }
+
+    #[test]
+    fn redirects_to_port_443_without_a_port() {
+        let target = RedirectTarget::Port(443);
+        assert_eq!(
+            redirect(&target, Some("example.com:80"), "/v1/config"),
+            "https://example.com/v1/config"
+        );
+        assert_eq!(
+            redirect(&target, Some("[::1]:80"), "/v1/config"),
+            "https://[::1]/v1/config"
+        );
+    }
+
+    #[test]
+    fn redirects_ipv6_hosts() {
+        let target = RedirectTarget::Port(3443);
+        assert_eq!(
+            redirect(&target, Some("[::1]:3000"), "/v1/config"),
+            "https://[::1]:3443/v1/config"
+        );
+        assert_eq!(
+            redirect(&target, Some("[2001:db8::1]"), "/"),
+            "https://[2001:db8::1]:3443/"
+        );
+    }
+
+    #[test]
+    fn redirects_to_the_public_url() {
+        let target = RedirectTarget::Url(Uri::from_static("https://kore.example.com/api/"));
+        assert_eq!(
+            redirect(&target, Some("10.0.0.1:3000"), "/v1/subjects?quantity=10"),
+            "https://kore.example.com/api/v1/subjects?quantity=10"
+        );
+
+        let target = RedirectTarget::Url(Uri::from_static("https://kore.example.com:8443"));
+        assert_eq!(
+            redirect(&target, None, "/v1/config"),
+            "https://kore.example.com:8443/v1/config"
+        );
+        assert_eq!(
+            redirect(&target, None, "/"),
+            "https://kore.example.com:8443/"
+        );
+    }
+}
@@ -1,38 +1,43 @@
-use std::{net::SocketAddr, path::PathBuf};
-
-use axum::{
-    BoxError,
-    handler::HandlerWithoutStateExt,
-    http::{Method, StatusCode, Uri, header},
-    response::Redirect,
-};
-use axum_extra::extract::Host;
-use axum_server::{Handle, tls_rustls::RustlsConfig};
-use enviroment::{
-    build_address_http, build_address_https, build_https_cert, build_https_private_key,
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
 };
+
+use axum::http::{HeaderName, Method, header};
 use kore_bridge::{
-    Bridge,
-    clap::Parser,
+    Bridge, clap,
+    clap::{Parser, Subcommand},
     settings::{build_config, build_file_path, build_password, command::Args},
 };
-use middleware::tower_trace;
-use server::build_routes;
-use tokio::net::TcpListener;
+use kore_http::{
+    bundle::verify_bundle,
+    cache::ResponseCache,
+    enviroment::{build_max_streams, build_shutdown_delay, build_shutdown_timeout},
+    listener::{listeners, serve},
+    middleware::{StreamLimit, tower_trace},
+    server::build_routes,
+    shutdown::{Shutdown, termination_signal},
+};
+#[cfg(feature = "grpc")]
+use kore_http::{enviroment::build_address_grpc, grpc::serve_grpc};
 use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::EnvFilter;
 
-mod enviroment;
-mod error;
-mod middleware;
-mod server;
-mod wrappers;
-
-mod doc;
-#[derive(Clone)]
-struct Ports {
-    http: String,
-    https: String,
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks the consistency of a bundle exported from a subject and prints the report.
+    Verify {
+        /// The bundle as a zip file.
+        bundle: PathBuf,
+    },
 }
 
 #[tokio::main]
@@ -42,7 +47,10 @@ async fn main() {
         .try_init()
         .unwrap();
 
-    let args = Args::parse();
+    let Cli { command, args } = Cli::parse();
+    if let Some(Command::Verify { bundle }) = command {
+        verify(&bundle);
+    }
 
     let mut password = args.password;
     if password.is_empty() {
@@ -54,105 +62,119 @@ async fn main() {
         file_path = build_file_path();
     }
 
-    let https_address = build_address_https();
-
-    let listener_http = tokio::net::TcpListener::bind(build_address_http())
-        .await
-        .unwrap();
+    let listeners = match listeners() {
+        Ok(listeners) => listeners,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH])
-        .allow_headers([header::CONTENT_TYPE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+        ])
+        .expose_headers([
+            header::ETAG,
+            header::LINK,
+            header::LOCATION,
+            HeaderName::from_static("x-total-count"),
+            HeaderName::from_static("deprecation"),
+            HeaderName::from_static("sunset"),
+        ])
         .allow_origin(Any);
 
     let config = build_config(args.env_config, &file_path).unwrap();
-    let bridge = Bridge::build(config, &password, None).await.unwrap();
+    let bridge = Arc::new(Bridge::build(config, &password, None).await.unwrap());
     let token = bridge.token().clone();
-
-    if !https_address.is_empty() {
-        let https_address = https_address.parse::<SocketAddr>().unwrap();
-
-        tokio::spawn(redirect_http_to_https(https_address.port(), listener_http));
-        rustls::crypto::ring::default_provider()
-            .install_default()
-            .unwrap();
-
-        let tls = RustlsConfig::from_pem_file(
-            PathBuf::from(&build_https_cert()),
-            PathBuf::from(&build_https_private_key()),
-        )
-        .await
-        .unwrap();
-
-        let handle = Handle::new();
-
-        let handle_clone = handle.clone();
-        tokio::spawn(async move {
+    let cache = ResponseCache::build(bridge.clone());
+    let streams = StreamLimit::new(build_max_streams());
+
+    // A signal or the node stopping starts the shutdown: readiness fails and subscriptions end,
+    // after the delay the listeners stop accepting connections and drain them, and then the
+    // node is stopped.
+    let shutdown = Shutdown::new(build_shutdown_delay());
+    let shutdown_timeout = build_shutdown_timeout();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        let token = token.clone();
+        async move {
             tokio::select! {
-                _ = token.cancelled() => {
-                    handle.graceful_shutdown(None);
-                }
+                _ = termination_signal() => {}
+                _ = token.cancelled() => {}
             }
-        });
-
-        axum_server::bind_rustls(https_address, tls)
-            .handle(handle_clone)
-            .serve(
-                tower_trace(build_routes(bridge))
-                    .layer(cors)
-                    .into_make_service_with_connect_info::<SocketAddr>(),
-            )
-            .await
-            .unwrap();
-    } else {
-        axum::serve(
-            listener_http,
-            tower_trace(build_routes(bridge))
-                .layer(cors)
-                .into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .with_graceful_shutdown(async move {
-            tokio::select! {
-                _ = token.cancelled() => {
+            shutdown.start();
+        }
+    });
+
+    // With an address of its own, the gRPC service is served apart and stops with the rest.
+    #[cfg(feature = "grpc")]
+    let grpc = {
+        let address = build_address_grpc();
+        (!address.is_empty()).then(|| {
+            let bridge = bridge.clone();
+            let cache = cache.clone();
+            let streams = streams.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                let result = serve_grpc(
+                    bridge,
+                    cache,
+                    streams,
+                    address,
+                    shutdown.clone(),
+                    shutdown_timeout,
+                )
+                .await;
+                if result.is_err() {
+                    shutdown.start();
                 }
-            }
+                result
+            })
         })
-        .await
-        .unwrap()
-    }
-}
-
-async fn redirect_http_to_https(https: u16, listener_http: TcpListener) {
-    fn make_https(host: String, uri: Uri, ports: Ports) -> Result<Uri, BoxError> {
-        let mut parts = uri.into_parts();
+    };
 
-        parts.scheme = Some(axum::http::uri::Scheme::HTTPS);
+    let routes = tower_trace(build_routes(bridge, cache, streams, shutdown.clone())).layer(cors);
 
-        if parts.path_and_query.is_none() {
-            parts.path_and_query = Some("/".parse().unwrap());
-        }
+    let result = serve(listeners, routes, shutdown.clone(), shutdown_timeout).await;
+    shutdown.start();
 
-        let https_host = host.replace(&ports.http.to_string(), &ports.https.to_string());
-        parts.authority = Some(https_host.parse()?);
+    #[cfg(feature = "grpc")]
+    let result = match grpc {
+        Some(grpc) => result.and(grpc.await.map_err(|e| e.to_string()).and_then(|x| x)),
+        None => result,
+    };
+    token.cancel();
 
-        Ok(Uri::from_parts(parts)?)
+    if let Err(e) = result {
+        tracing::error!("{}", e);
+        std::process::exit(1);
     }
-    let ports = Ports {
-        https: https.to_string(),
-        http: listener_http.local_addr().unwrap().port().to_string(),
-    };
+}
 
-    let redirect = move |Host(host): Host, uri: Uri| async move {
-        match make_https(host, uri, ports) {
-            Ok(uri) => Ok(Redirect::permanent(&uri.to_string())),
-            Err(error) => {
-                tracing::warn!(%error, "failed to convert URI to HTTPS");
-                Err(StatusCode::BAD_REQUEST)
-            }
+fn verify(path: &Path) -> ! {
+    let bundle = match std::fs::read(path) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path.display(), e);
+            std::process::exit(2);
         }
     };
 
-    axum::serve(listener_http, redirect.into_make_service())
-        .await
-        .unwrap();
+    let report = verify_bundle(&bundle);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).unwrap_or_default()
+    );
+
+    std::process::exit(if report.errors.is_empty() { 0 } else { 1 });
 }
@@ -1,14 +1,423 @@
-use std::time::Duration;
-use tower_http::{classify::ServerErrorsFailureClass, trace::TraceLayer};
-use tracing::{Span, debug, error, info_span};
+use std::{sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{
+    BoxError, ServiceBuilder, limit::GlobalConcurrencyLimitLayer, load_shed::error::Overloaded,
+    timeout::error::Elapsed,
+};
+use tower_http::{
+    classify::ServerErrorsFailureClass,
+    compression::{
+        CompressionLayer, Predicate,
+        predicate::{NotForContentType, SizeAbove},
+    },
+    decompression::RequestDecompressionLayer,
+    timeout::RequestBodyTimeoutLayer,
+    trace::TraceLayer,
+};
+use tracing::{Span, debug, error, info_span, warn};
 
 use axum::{
-    Router,
-    body::Bytes,
-    extract::{MatchedPath, Request},
-    http::HeaderMap,
-    response::Response,
+    Json, Router,
+    body::{Body, Bytes, to_bytes},
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, MatchedPath, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    middleware::{Next, from_fn},
+    response::{IntoResponse, Response},
 };
+use serde_json::Value;
+
+/// Marks every response of `routes` as deprecated, with a `Deprecation` header (RFC 9745) with
+/// the given structured field date, a `Sunset` header (RFC 8594) with the given HTTP date and a
+/// `Link` to the same path under `prefix`.
+pub fn deprecated_alias(
+    routes: Router,
+    prefix: &'static str,
+    deprecated_since: String,
+    sunset: String,
+) -> Router {
+    let deprecation = HeaderValue::from_str(&deprecated_since).ok();
+    let sunset = HeaderValue::from_str(&sunset).ok();
+
+    routes.layer(from_fn(move |request: Request, next: Next| {
+        let deprecation = deprecation.clone();
+        let sunset = sunset.clone();
+        async move {
+            let successor = format!(
+                "<{}{}>; rel=\"successor-version\"",
+                prefix,
+                request.uri().path()
+            );
+            let mut response = next.run(request).await;
+
+            let headers = response.headers_mut();
+            if let Some(deprecation) = deprecation {
+                headers.insert(HeaderName::from_static("deprecation"), deprecation);
+            }
+            if let Some(sunset) = sunset {
+                headers.insert(HeaderName::from_static("sunset"), sunset);
+            }
+            if let Ok(link) = HeaderValue::from_str(&successor) {
+                headers.append(header::LINK, link);
+            }
+            response
+        }
+    }))
+}
+
+/// Suffix appended to the entity tags of the responses compressed with each content coding.
+const ENCODING_SUFFIXES: [(&str, &str); 3] = [("gzip", ".gz"), ("br", ".br"), ("zstd", ".zst")];
+
+/// Compresses the responses of `routes` larger than `min_size` bytes with gzip, brotli or zstd,
+/// as negotiated with `Accept-Encoding`, and decompresses request bodies sent with any of them
+/// in `Content-Encoding`. gRPC, images and event streams are never compressed.
+///
+/// A compressed response is not the same bytes as the identity one, so its entity tag gets the
+/// content coding appended, which is removed from the tags of `If-None-Match` before they reach
+/// the handlers.
+pub fn compression(routes: Router, min_size: u16) -> Router {
+    let predicate = SizeAbove::new(min_size)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE);
+
+    routes
+        .layer(CompressionLayer::new().compress_when(predicate))
+        .layer(RequestDecompressionLayer::new())
+        .layer(from_fn(tag_encoding))
+}
+
+async fn tag_encoding(mut request: Request, next: Next) -> Response {
+    // The suffix of the tags a client sent back, which a 304 keeps as it has no body to compress.
+    let mut requested = None;
+    let tags: Vec<String> = request
+        .headers()
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .map(|tag| {
+            let tag = tag.trim();
+            ENCODING_SUFFIXES
+                .iter()
+                .find_map(|(_, suffix)| {
+                    let untagged = tag.strip_suffix('"')?.strip_suffix(suffix)?;
+                    requested = Some(*suffix);
+                    Some(format!("{}\"", untagged))
+                })
+                .unwrap_or_else(|| tag.to_owned())
+        })
+        .collect();
+
+    if requested.is_some()
+        && let Ok(value) = HeaderValue::from_str(&tags.join(", "))
+    {
+        request.headers_mut().insert(header::IF_NONE_MATCH, value);
+    }
+
+    let mut response = next.run(request).await;
+
+    let suffix = if response.status() == StatusCode::NOT_MODIFIED {
+        requested
+    } else {
+        response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|encoding| {
+                ENCODING_SUFFIXES
+                    .iter()
+                    .find(|(name, _)| encoding.eq_ignore_ascii_case(name))
+                    .map(|(_, suffix)| *suffix)
+            })
+    };
+
+    if let Some(suffix) = suffix
+        && let Some(tag) = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|x| x.to_str().ok())
+        && let Ok(tag) =
+            HeaderValue::from_str(&format!("{}{}\"", tag.trim_end_matches('"'), suffix))
+    {
+        response.headers_mut().insert(header::ETAG, tag);
+    }
+    response
+}
+
+/// Rejects the request bodies of `routes` larger than `max_size` bytes, once decompressed, with
+/// a 413, unless a route sets its own limit. A client that takes longer than `read_timeout` to
+/// send the body has its request aborted, so slow clients can not hold a handler.
+pub fn body_limits(routes: Router, max_size: usize, read_timeout: Duration) -> Router {
+    routes
+        .layer(DefaultBodyLimit::max(max_size))
+        .layer(RequestBodyTimeoutLayer::new(read_timeout))
+}
+
+/// Answers with a 504 the requests of `routes` that take longer than `timeout` to produce the
+/// response headers, and with a 503 those that arrive while `max_concurrency` requests are
+/// already being handled, instead of queueing them. Streamed bodies are not limited by either,
+/// the routes that stream are bounded apart with [`limit_stream`].
+pub fn load_shedding(routes: Router, timeout: Duration, max_concurrency: usize) -> Router {
+    routes.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(handle_overload))
+            .load_shed()
+            .layer(GlobalConcurrencyLimitLayer::new(max_concurrency))
+            .timeout(timeout),
+    )
+}
+
+async fn handle_overload(error: BoxError) -> Response {
+    if error.is::<Elapsed>() {
+        warn!("Request timed out");
+        (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json("The request took too long to complete"),
+        )
+            .into_response()
+    } else if error.is::<Overloaded>() {
+        warn!("Request rejected, too many requests in progress");
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            Json("The server is handling too many requests"),
+        )
+            .into_response()
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(error.to_string())).into_response()
+    }
+}
+
+/// Bound on the responses that stay open after their headers are sent, shared by every route
+/// it is layered on with [`limit_stream`].
+#[derive(Clone)]
+pub struct StreamLimit(Arc<Semaphore>);
+
+impl StreamLimit {
+    pub fn new(max_streams: usize) -> Self {
+        Self(Arc::new(Semaphore::new(max_streams)))
+    }
+
+    /// Takes the permit of a new stream, unless the limit is reached.
+    pub fn try_acquire(&self) -> Option<StreamPermit> {
+        let permit = self.0.clone().try_acquire_owned().ok()?;
+        Some(StreamPermit(Arc::new(permit)))
+    }
+}
+
+/// Permit of an open stream, held until it ends. Handlers that upgrade the connection take it
+/// from the request extensions and keep it for as long as the socket is open.
+#[derive(Clone)]
+pub struct StreamPermit(#[allow(dead_code)] Arc<OwnedSemaphorePermit>);
+
+/// Answers with a 503 the requests of a streaming route, such as exports and sockets, that
+/// arrive while the limit of open streams is reached. The permit of a stream is released when
+/// its body ends or, for an upgraded connection, when the handler drops its [`StreamPermit`].
+pub async fn limit_stream(
+    State(limit): State<StreamLimit>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(permit) = limit.try_acquire() else {
+        warn!("Request rejected, too many open streams");
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            Json("The server has too many open streams"),
+        )
+            .into_response();
+    };
+    request.extensions_mut().insert(permit.clone());
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _ = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+/// Format of the responses of the API, negotiated with `Accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl Format {
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type {
+            "application/json" | "application/*" | "*/*" => Some(Format::Json),
+            "application/cbor" => Some(Format::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Format::MessagePack)
+            }
+            _ => None,
+        }
+    }
+
+    /// The format with the highest quality in an `Accept` header, JSON if none is supported.
+    fn from_accept(headers: &HeaderMap) -> Self {
+        let mut best = (Format::Json, 0.0);
+        for item in headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|x| x.to_str().ok())
+            .flat_map(|x| x.split(','))
+        {
+            let mut params = item.split(';').map(str::trim);
+            let Some(format) = params
+                .next()
+                .and_then(|x| Self::from_media_type(&x.to_ascii_lowercase()))
+            else {
+                continue;
+            };
+            let quality = params
+                .find_map(|x| x.strip_prefix("q="))
+                .and_then(|x| x.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if quality > best.1 {
+                best = (format, quality);
+            }
+        }
+        best.0
+    }
+
+    fn media_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Cbor => "application/cbor",
+            Format::MessagePack => "application/msgpack",
+        }
+    }
+
+    fn encode(&self, value: &Value) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Format::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            Format::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn tag_suffix(&self) -> Option<&'static str> {
+        match self {
+            Format::Json => None,
+            Format::Cbor => Some(".cbor"),
+            Format::MessagePack => Some(".msgpack"),
+        }
+    }
+
+    /// The entity tag of the representation in this format of the JSON one tagged `tag`.
+    fn tag(&self, tag: &str) -> String {
+        match self.tag_suffix() {
+            Some(suffix) => format!("{}{}\"", tag.trim_end_matches('"'), suffix),
+            None => tag.to_owned(),
+        }
+    }
+
+    /// The entity tag of the JSON representation of the one in this format tagged `tag`.
+    fn untag(&self, tag: &str) -> String {
+        match self
+            .tag_suffix()
+            .and_then(|suffix| tag.strip_suffix('"')?.strip_suffix(suffix))
+        {
+            Some(tag) => format!("{}\"", tag),
+            None => tag.to_owned(),
+        }
+    }
+}
+
+/// Encodes the JSON responses of `routes` as CBOR or MessagePack when the request prefers it
+/// in `Accept`. Entity tags get the format appended, and it is removed from the tags of
+/// `If-None-Match` before they reach the handlers.
+pub fn negotiate_format(routes: Router) -> Router {
+    routes.layer(from_fn(|mut request: Request, next: Next| async move {
+        let format = Format::from_accept(request.headers());
+
+        if format != Format::Json {
+            let tags: Vec<String> = request
+                .headers()
+                .get_all(header::IF_NONE_MATCH)
+                .iter()
+                .filter_map(|x| x.to_str().ok())
+                .flat_map(|x| x.split(','))
+                .map(|tag| format.untag(tag.trim()))
+                .collect();
+
+            if !tags.is_empty()
+                && let Ok(value) = HeaderValue::from_str(&tags.join(", "))
+            {
+                request.headers_mut().insert(header::IF_NONE_MATCH, value);
+            }
+        }
+
+        let response = next.run(request).await;
+        encode_response(response, format).await
+    }))
+}
+
+async fn encode_response(response: Response, format: Format) -> Response {
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    if format == Format::Json {
+        return Response::from_parts(parts, body);
+    }
+
+    if let Some(tag) = parts
+        .headers
+        .get(header::ETAG)
+        .and_then(|x| x.to_str().ok())
+        && let Ok(tag) = HeaderValue::from_str(&format.tag(tag))
+    {
+        parts.headers.insert(header::ETAG, tag);
+    }
+
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("application/json"));
+    if !is_json {
+        return Response::from_parts(parts, body);
+    }
+
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let Ok(value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    match format.encode(&value) {
+        Ok(encoded) => {
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(format.media_type()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(encoded))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
 
 pub fn tower_trace(routes: Router) -> Router {
     routes.layer(
@@ -51,3 +460,76 @@ pub fn tower_trace(routes: Router) -> Router {
             ),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const TAG: &str = "\"a.1\"";
+
+    fn routes() -> Router {
+        let routes = Router::new().route(
+            "/",
+            get(|headers: HeaderMap| async move {
+                let tag = HeaderValue::from_static(TAG);
+                if headers.get(header::IF_NONE_MATCH) == Some(&tag) {
+                    return (StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response();
+                }
+                ([(header::ETAG, tag)], "a".repeat(1024)).into_response()
+            }),
+        );
+        compression(routes, 32)
+    }
+
+    async fn send(headers: &[(HeaderName, &str)]) -> Response {
+        let mut request = Request::builder().uri("/");
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        routes()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn compressed_responses_have_their_own_tag() {
+        let response = send(&[(header::ACCEPT_ENCODING, "gzip")]).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::ETAG], "\"a.1.gz\"");
+    }
+
+    #[tokio::test]
+    async fn suffixed_tags_are_not_modified() {
+        let response = send(&[
+            (header::ACCEPT_ENCODING, "gzip"),
+            (header::IF_NONE_MATCH, "\"a.1.gz\""),
+        ])
+        .await;
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], "\"a.1.gz\"");
+    }
+
+    #[tokio::test]
+    async fn identity_keeps_the_bare_tag() {
+        let response = send(&[(header::ACCEPT_ENCODING, "identity")]).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(response.headers()[header::ETAG], TAG);
+
+        let response = send(&[
+            (header::ACCEPT_ENCODING, "identity"),
+            (header::IF_NONE_MATCH, TAG),
+        ])
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], TAG);
+    }
+}
@@ -0,0 +1,223 @@
+use axum::http::{HeaderMap, HeaderValue};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// Maximum number of items returned in a single page.
+pub const MAX_LIMIT: u64 = 500;
+/// Default number of items returned in a single page.
+pub const DEFAULT_LIMIT: u64 = 50;
+/// Maximum number of events read from the node to fill a filtered page.
+pub const MAX_SCAN: u64 = 1000;
+/// Number of events read from the node in a single call when scanning the events of a subject.
+pub const EVENTS_BATCH: u64 = 100;
+/// Maximum number of subjects whose state is read to filter or sort a listing.
+pub const MAX_DETAILS: u64 = 500;
+/// Number of subjects whose state is read at the same time to filter or sort a listing.
+pub const DETAILS_CONCURRENCY: usize = 8;
+
+/// Position of a page relative to a sequence number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCursor {
+    /// Items with a sequence number greater than the given one.
+    After(u64),
+    /// Items with a sequence number lower than the given one.
+    Before(u64),
+}
+
+impl PageCursor {
+    /// Encodes the cursor as an opaque string for clients.
+    pub fn encode(&self) -> String {
+        let raw = match self {
+            PageCursor::After(sn) => format!("a:{}", sn),
+            PageCursor::Before(sn) => format!("b:{}", sn),
+        };
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// Decodes a cursor previously returned by [`PageCursor::encode`].
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let invalid = || Error::BadRequest(format!("Invalid cursor: {}", cursor));
+
+        let raw = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (direction, sn) = raw.split_once(':').ok_or_else(invalid)?;
+        let sn = sn.parse::<u64>().map_err(|_| invalid())?;
+
+        match direction {
+            "a" => Ok(PageCursor::After(sn)),
+            "b" => Ok(PageCursor::Before(sn)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Clamps the requested page size to the allowed range.
+pub fn limit(limit: Option<u64>) -> u64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Builds the value of a `Link` header from the next and previous page urls.
+pub fn link_header(next: Option<String>, prev: Option<String>) -> Option<String> {
+    let links: Vec<String> = [(next, "next"), (prev, "prev")]
+        .into_iter()
+        .filter_map(|(url, rel)| url.map(|url| format!("<{}>; rel=\"{}\"", url, rel)))
+        .collect();
+
+    if links.is_empty() {
+        None
+    } else {
+        Some(links.join(", "))
+    }
+}
+
+/// Url of a page of the listing at `path`, with its query URL-encoded as the listing reads it.
+pub fn page_url<Q: Serialize>(path: &str, query: &Q) -> String {
+    match serde_urlencoded::to_string(query) {
+        Ok(query) if !query.is_empty() => format!("{}?{}", path, query),
+        _ => path.to_owned(),
+    }
+}
+
+/// Header with the total number of items matching a listing, before paging.
+pub const TOTAL_COUNT: &str = "x-total-count";
+
+/// Headers with the total number of items matching a listing, before paging.
+pub fn total_count(total: usize) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(TOTAL_COUNT, HeaderValue::from(total));
+    headers
+}
+
+/// Returns the given page of a listing, 1-based. Every item is returned if no quantity is set.
+pub fn page<T>(items: Vec<T>, quantity: Option<u64>, page: Option<u64>) -> Vec<T> {
+    let Some(quantity) = quantity else {
+        return items;
+    };
+    let quantity = quantity.clamp(1, MAX_LIMIT) as usize;
+    let page = page.unwrap_or(1).max(1) as usize;
+
+    items
+        .into_iter()
+        .skip((page - 1).saturating_mul(quantity))
+        .take(quantity)
+        .collect()
+}
+
+/// Checks whether the name or the description contain the search text, ignoring case.
+pub fn matches_search(name: &Option<String>, description: &Option<String>, search: &str) -> bool {
+    let search = search.to_lowercase();
+    [name, description]
+        .into_iter()
+        .flatten()
+        .any(|x| x.to_lowercase().contains(&search))
+}
+
+/// Checks whether a namespace is the given prefix or is nested under it.
+pub fn matches_namespace(namespace: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('.');
+    prefix.is_empty()
+        || namespace == prefix
+        || namespace
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    use super::*;
+    use crate::{server::EventsCursorQuery, wrappers::EventType};
+
+    #[test]
+    fn cursors_are_decoded_as_encoded() {
+        for cursor in [
+            PageCursor::After(0),
+            PageCursor::After(42),
+            PageCursor::Before(u64::MAX),
+        ] {
+            assert_eq!(PageCursor::decode(&cursor.encode()).unwrap(), cursor);
+        }
+        assert_ne!(
+            PageCursor::After(1).encode(),
+            PageCursor::Before(1).encode()
+        );
+    }
+
+    #[test]
+    fn malformed_cursors_are_bad_requests() {
+        let cursors = [
+            "not base64!".to_owned(),
+            URL_SAFE_NO_PAD.encode("a"),
+            URL_SAFE_NO_PAD.encode("a:-1"),
+            URL_SAFE_NO_PAD.encode("c:1"),
+            URL_SAFE_NO_PAD.encode([0xff, b':', b'1']),
+        ];
+
+        for cursor in cursors {
+            let Err(error) = PageCursor::decode(&cursor) else {
+                panic!("{} was decoded", cursor);
+            };
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn link_header_lists_the_adjacent_pages() {
+        assert_eq!(link_header(None, None), None);
+        assert_eq!(
+            link_header(Some("/a?cursor=b".to_owned()), None).as_deref(),
+            Some("</a?cursor=b>; rel=\"next\"")
+        );
+        assert_eq!(
+            link_header(Some("/next".to_owned()), Some("/prev".to_owned())).as_deref(),
+            Some("</next>; rel=\"next\", </prev>; rel=\"prev\"")
+        );
+    }
+
+    #[test]
+    fn total_count_header_holds_the_total() {
+        let headers = total_count(1234);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[TOTAL_COUNT], "1234");
+    }
+
+    #[test]
+    fn page_urls_are_read_back_as_their_query() {
+        let query = EventsCursorQuery {
+            cursor: Some(PageCursor::After(7).encode()),
+            limit: Some(20),
+            event_type: Some(EventType::EOL),
+            success: Some(false),
+            ..Default::default()
+        };
+
+        let url = page_url("/v1/events/Jsubject", &query);
+        let (path, raw) = url.split_once('?').unwrap();
+        assert_eq!(path, "/v1/events/Jsubject");
+        assert!(raw.contains("event_type=EOL"), "{}", raw);
+
+        let read: EventsCursorQuery = serde_urlencoded::from_str(raw).unwrap();
+        assert_eq!(read.cursor, query.cursor);
+        assert_eq!(read.limit, Some(20));
+        assert_eq!(read.event_type, Some(EventType::EOL));
+        assert_eq!(read.success, Some(false));
+        assert_eq!((read.after_sn, read.before_sn), (None, None));
+
+        assert_eq!(
+            page_url("/v1/events/Jsubject", &EventsCursorQuery::default()),
+            "/v1/events/Jsubject"
+        );
+    }
+
+    #[test]
+    fn pages_are_one_based() {
+        let items: Vec<u32> = (0..10).collect();
+        assert_eq!(page(items.clone(), Some(4), None), [0, 1, 2, 3]);
+        assert_eq!(page(items.clone(), Some(4), Some(3)), [8, 9]);
+        assert!(page(items.clone(), Some(4), Some(4)).is_empty());
+        assert_eq!(page(items, None, Some(2)).len(), 10);
+    }
+}
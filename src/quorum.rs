@@ -0,0 +1,353 @@
+use std::collections::HashSet;
+
+use crate::{
+    pagination::matches_namespace,
+    wrappers::{
+        Governance, ProtocolQuorum, ProtocolsSignaturesInfo, Quorum, RoleType, SignaturesInfo,
+        SignaturesQuorum, Signer, SubjectInfo, TimedOutSigner, Who,
+    },
+};
+
+/// Members of a governance that may sign for a role.
+struct Eligible {
+    ids: Vec<String>,
+    members: HashSet<String>,
+    all: bool,
+    not_members: bool,
+}
+
+impl Eligible {
+    fn new(governance: &Governance, role: RoleType, schema_id: &str, namespace: &str) -> Self {
+        let mut eligible = Eligible {
+            ids: vec![],
+            members: governance.members.iter().map(|x| x.id.clone()).collect(),
+            all: false,
+            not_members: false,
+        };
+
+        let roles = governance.roles.iter().filter(|x| {
+            x.role == role
+                && x.schema.applies_to(schema_id)
+                && matches_namespace(namespace, &x.namespace)
+        });
+
+        for role in roles {
+            match &role.who {
+                Who::Id(id) => eligible.ids.push(id.clone()),
+                Who::Name(name) => eligible.ids.extend(
+                    governance
+                        .members
+                        .iter()
+                        .filter(|x| &x.name == name)
+                        .map(|x| x.id.clone()),
+                ),
+                Who::Members => eligible
+                    .ids
+                    .extend(governance.members.iter().map(|x| x.id.clone())),
+                Who::All => eligible.all = true,
+                Who::NotMembers => eligible.not_members = true,
+            }
+        }
+
+        eligible.ids.sort();
+        eligible.ids.dedup();
+        eligible
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.all
+            || self.ids.iter().any(|x| x == id)
+            || (self.not_members && !self.members.contains(id))
+    }
+}
+
+/// Resolves the signatures of an event against the roles of the governance and
+/// checks whether the quorum of each protocol was reached.
+///
+/// The governance must be the version in force when the event was signed.
+pub fn analyze(
+    governance: &Governance,
+    subject: &SubjectInfo,
+    signatures: SignaturesInfo,
+) -> Result<SignaturesQuorum, String> {
+    let Some(policy) = governance
+        .policies
+        .iter()
+        .find(|x| x.id == subject.schema_id)
+    else {
+        return Err(format!(
+            "The governance has no policy for schema {}",
+            subject.schema_id
+        ));
+    };
+
+    let protocol = |role: RoleType, quorum: &Quorum, set: HashSet<ProtocolsSignaturesInfo>| {
+        let eligible = Eligible::new(governance, role, &subject.schema_id, &subject.namespace);
+        analyze_protocol(governance, &eligible, quorum, set)
+    };
+
+    Ok(SignaturesQuorum {
+        subject_id: signatures.subject_id,
+        sn: signatures.sn,
+        governance_version: governance.version,
+        evaluation: signatures
+            .signatures_eval
+            .map(|set| protocol(RoleType::Evaluator, &policy.evaluate.quorum, set)),
+        approval: signatures
+            .signatures_appr
+            .map(|set| protocol(RoleType::Approver, &policy.approve.quorum, set)),
+        validation: protocol(
+            RoleType::Validator,
+            &policy.validate.quorum,
+            signatures.signatures_vali,
+        ),
+    })
+}
+
+fn analyze_protocol(
+    governance: &Governance,
+    eligible: &Eligible,
+    quorum: &Quorum,
+    set: HashSet<ProtocolsSignaturesInfo>,
+) -> ProtocolQuorum {
+    let name = |id: &str| {
+        governance
+            .members
+            .iter()
+            .find(|x| x.id == id)
+            .map(|x| x.name.clone())
+    };
+
+    let mut signers = vec![];
+    let mut timed_out = vec![];
+    for signature in set {
+        match signature {
+            ProtocolsSignaturesInfo::Signature(signature) => signers.push(Signer {
+                name: name(&signature.signer),
+                eligible: eligible.contains(&signature.signer),
+                signer: signature.signer,
+                timestamp: signature.timestamp,
+            }),
+            ProtocolsSignaturesInfo::TimeOut(timeout) => timed_out.push(TimedOutSigner {
+                name: name(&timeout.who),
+                who: timeout.who,
+                re_trys: timeout.re_trys,
+                timestamp: timeout.timestamp,
+            }),
+        }
+    }
+    signers.sort_by(|a, b| a.signer.cmp(&b.signer));
+    timed_out.sort_by(|a, b| a.who.cmp(&b.who));
+
+    // The controllers of an open role can not be enumerated, so the majority and percentage
+    // are taken over the eligible controllers the node asked, whether they signed or timed out.
+    let open = eligible.all || eligible.not_members;
+    let total = if open {
+        let mut asked: HashSet<&str> = eligible.ids.iter().map(String::as_str).collect();
+        asked.extend(
+            signers
+                .iter()
+                .filter(|x| x.eligible)
+                .map(|x| x.signer.as_str()),
+        );
+        asked.extend(
+            timed_out
+                .iter()
+                .filter(|x| eligible.contains(&x.who))
+                .map(|x| x.who.as_str()),
+        );
+        asked.len() as u64
+    } else {
+        eligible.ids.len() as u64
+    };
+
+    // A quorum is never reached without an eligible signature.
+    let required = match quorum {
+        Quorum::Majority => total / 2 + 1,
+        Quorum::Fixed(fixed) => u64::from(*fixed),
+        Quorum::Percentage(percentage) => (percentage * total as f64).ceil() as u64,
+    }
+    .max(1);
+    let signed = signers.iter().filter(|x| x.eligible).count() as u64;
+
+    ProtocolQuorum {
+        quorum: quorum.clone(),
+        open,
+        eligible: eligible.ids.clone(),
+        required,
+        reached: signed >= required,
+        signers,
+        timed_out,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::wrappers::{
+        Member, Policy, Role, SchemaEnum, SignatureInfo, TimeOutResponseInfo, Validation,
+    };
+
+    fn governance(who: Who, schema: SchemaEnum, quorum: Quorum) -> Governance {
+        let validation = |quorum: &Quorum| Validation {
+            quorum: quorum.clone(),
+        };
+
+        Governance {
+            version: 3,
+            members: [("E1", "a"), ("E2", "b"), ("E3", "c")]
+                .into_iter()
+                .map(|(id, name)| Member {
+                    id: id.to_owned(),
+                    name: name.to_owned(),
+                })
+                .collect(),
+            roles: vec![Role {
+                who,
+                namespace: String::new(),
+                role: RoleType::Validator,
+                schema,
+            }],
+            schemas: vec![],
+            policies: vec![Policy {
+                id: "example".to_owned(),
+                evaluate: validation(&quorum),
+                approve: validation(&quorum),
+                validate: validation(&quorum),
+            }],
+        }
+    }
+
+    fn subject() -> SubjectInfo {
+        SubjectInfo {
+            subject_id: "Jsubject".to_owned(),
+            governance_id: "Jgovernance".to_owned(),
+            genesis_gov_version: 0,
+            namespace: String::new(),
+            schema_id: "example".to_owned(),
+            owner: "E1".to_owned(),
+            creator: "E1".to_owned(),
+            active: true,
+            sn: 1,
+            properties: json!({}),
+            new_owner: None,
+            name: String::new(),
+            description: String::new(),
+        }
+    }
+
+    fn signed(signer: &str) -> ProtocolsSignaturesInfo {
+        ProtocolsSignaturesInfo::Signature(SignatureInfo {
+            signer: signer.to_owned(),
+            timestamp: 0,
+            content_hash: String::new(),
+            value: String::new(),
+        })
+    }
+
+    fn timed_out(who: &str) -> ProtocolsSignaturesInfo {
+        ProtocolsSignaturesInfo::TimeOut(TimeOutResponseInfo {
+            who: who.to_owned(),
+            re_trys: 2,
+            timestamp: "0".to_owned(),
+        })
+    }
+
+    fn validation(
+        governance: &Governance,
+        signatures: impl IntoIterator<Item = ProtocolsSignaturesInfo>,
+    ) -> ProtocolQuorum {
+        let signatures = SignaturesInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn: 1,
+            signatures_eval: None,
+            signatures_appr: None,
+            signatures_vali: signatures.into_iter().collect(),
+        };
+
+        let quorum = analyze(governance, &subject(), signatures).unwrap();
+        assert_eq!(quorum.governance_version, 3);
+        assert!(quorum.evaluation.is_none() && quorum.approval.is_none());
+        quorum.validation
+    }
+
+    #[test]
+    fn quorum_is_reached_by_a_majority_of_the_eligible_members() {
+        let governance = governance(Who::Members, SchemaEnum::All, Quorum::Majority);
+        let quorum = validation(&governance, [signed("E1"), signed("E2")]);
+
+        assert!(quorum.reached);
+        assert!(!quorum.open);
+        assert_eq!(quorum.eligible, ["E1", "E2", "E3"]);
+        assert_eq!(quorum.required, 2);
+        assert_eq!(quorum.signers[0].name.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn signers_without_the_role_do_not_count() {
+        let governance = governance(Who::Name("a".to_owned()), SchemaEnum::All, Quorum::Fixed(2));
+        let quorum = validation(
+            &governance,
+            [signed("E1"), signed("E2"), signed("Eoutsider")],
+        );
+
+        assert!(!quorum.reached);
+        assert_eq!(quorum.eligible, ["E1"]);
+        assert_eq!(quorum.required, 2);
+        let eligible: Vec<bool> = quorum.signers.iter().map(|x| x.eligible).collect();
+        assert_eq!(eligible, [true, false, false]);
+        assert_eq!(quorum.signers[2].name, None);
+    }
+
+    #[test]
+    fn timeouts_count_towards_the_total_of_an_open_role() {
+        let governance = governance(Who::All, SchemaEnum::All, Quorum::Majority);
+        let quorum = validation(
+            &governance,
+            [signed("E1"), timed_out("E2"), timed_out("Eoutsider")],
+        );
+
+        assert!(quorum.open);
+        assert!(quorum.eligible.is_empty());
+        assert_eq!(quorum.required, 2);
+        assert!(!quorum.reached);
+        assert_eq!(quorum.timed_out.len(), 2);
+        assert_eq!(quorum.timed_out[0].name.as_deref(), Some("b"));
+        assert_eq!(quorum.timed_out[0].re_trys, 2);
+
+        let quorum = validation(&governance, [signed("E1"), signed("E2"), timed_out("E3")]);
+        assert!(quorum.reached);
+    }
+
+    #[test]
+    fn quorum_is_not_reached_without_a_matching_role() {
+        let governance = governance(
+            Who::Members,
+            SchemaEnum::Id("other".to_owned()),
+            Quorum::Percentage(0.5),
+        );
+        let quorum = validation(&governance, [signed("E1")]);
+
+        assert!(quorum.eligible.is_empty());
+        assert_eq!(quorum.required, 1);
+        assert!(!quorum.signers[0].eligible);
+        assert!(!quorum.reached);
+    }
+
+    #[test]
+    fn schema_without_a_policy_is_an_error() {
+        let mut governance = governance(Who::Members, SchemaEnum::All, Quorum::Majority);
+        governance.policies.clear();
+
+        let signatures = SignaturesInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn: 1,
+            signatures_eval: None,
+            signatures_appr: None,
+            signatures_vali: [signed("E1")].into_iter().collect(),
+        };
+        assert!(analyze(&governance, &subject(), signatures).is_err());
+    }
+}
@@ -1,59 +1,170 @@
 use std::{
+    collections::HashMap,
     io::{Cursor, Write},
-    sync::Arc,
+    sync::{Arc, OnceLock},
+    time::Duration,
 };
 
 use crate::{
-    enviroment::build_doc,
+    bundle::verify_bundle,
+    cache::ResponseCache,
+    enviroment::{
+        build_body_limit, build_body_timeout, build_compression_min_size, build_deprecated_since,
+        build_doc, build_max_concurrency, build_request_timeout, build_sunset,
+    },
     error::Error,
+    etag::{check_if_match, entity_tag, none_match, not_modified, tagged},
+    export::{content_type, export_bundle, export_events},
+    middleware::{
+        StreamLimit, body_limits, compression, deprecated_alias, limit_stream, load_shedding,
+        negotiate_format,
+    },
+    pagination::{
+        DETAILS_CONCURRENCY, EVENTS_BATCH, MAX_DETAILS, MAX_SCAN, PageCursor, limit, link_header,
+        matches_namespace, matches_search, page, page_url, total_count,
+    },
+    quorum::analyze,
+    shutdown::Shutdown,
+    state::{MAX_REPLAY, diff, project, properties_hash, query, replay},
+    validation::validate_event_request,
     wrappers::{
-        ApproveInfo, Config as ConfigKoreHttp, EventInfo, GovsData, PaginatorEvents, RegisterDataSubj,
-        RequestData, RequestInfo, SignaturesInfo, SubjectInfo, TransferSubject,
+        ApproveInfo, BundleReport, CacheMetrics, Config as ConfigKoreHttp, CursorEvents, EventInfo,
+        EventType, EventsPage, ExportFormat, Governance, GovsData, Member, PaginatorEvents, Policy,
+        ProtocolsSignaturesInfo, RegisterDataSubj, RequestData, RequestInfo, Role, RoleType,
+        Schema, SignaturesInfo, SignaturesQuorum, SortField, StateDiff, StateResponse, SubjectInfo,
+        SubjectState, TransferSubject,
     },
 };
 use axum::{
     Extension, Json, Router,
     body::Body,
-    extract::{Path, Query},
-    http::{StatusCode, header},
+    extract::{DefaultBodyLimit, Path, Query},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::from_fn_with_state,
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post, put},
 };
 use bytes::Bytes;
-use kore_bridge::{Bridge, model::BridgeSignedEventRequest};
-use serde::Deserialize;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use kore_bridge::{
+    Bridge,
+    model::{BridgeEventRequest, BridgeSignedEventRequest},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tower::ServiceBuilder;
 use utoipa::ToSchema;
 use zip::{CompressionMethod, ZipWriter, write::FileOptions};
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct SubjectQuery {
-    active: Option<bool>,
-    schema: Option<String>,
+    pub active: Option<bool>,
+    pub schema: Option<String>,
+    pub namespace: Option<String>,
+    pub search: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: Option<bool>,
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct GovQuery {
-    active: Option<bool>,
+    pub active: Option<bool>,
+    pub search: Option<String>,
+    pub sort: Option<SortField>,
+    pub reverse: Option<bool>,
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RoleQuery {
+    pub role: Option<RoleType>,
+    pub schema: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct EventsQuery {
-    quantity: Option<u64>,
-    page: Option<u64>,
+    pub quantity: Option<u64>,
+    pub page: Option<u64>,
+    pub after_sn: Option<u64>,
+    pub before_sn: Option<u64>,
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+    pub event_type: Option<EventType>,
+    pub success: Option<bool>,
+}
+
+impl EventsQuery {
+    /// The cursor parameters of the query, if any of them is given.
+    fn cursor(&self) -> Option<EventsCursorQuery> {
+        let query = EventsCursorQuery {
+            after_sn: self.after_sn,
+            before_sn: self.before_sn,
+            cursor: self.cursor.clone(),
+            limit: self.limit,
+            event_type: self.event_type,
+            success: self.success,
+        };
+
+        let given = query.after_sn.is_some()
+            || query.before_sn.is_some()
+            || query.cursor.is_some()
+            || query.limit.is_some()
+            || query.event_type.is_some()
+            || query.success.is_some();
+        given.then_some(query)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct EventsCursorQuery {
+    pub after_sn: Option<u64>,
+    pub before_sn: Option<u64>,
+    pub cursor: Option<String>,
+    pub limit: Option<u64>,
+    pub event_type: Option<EventType>,
+    pub success: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct StateQuery {
+    pub sn: Option<u64>,
+    pub fields: Option<String>,
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct DiffQuery {
+    pub from: u64,
+    pub to: u64,
 }
 
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct SignaturesQuery {
+    pub sn: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct EventSnQuery {
-    sn: u64,
+    pub sn: u64,
+}
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ExportQuery {
+    pub format: Option<ExportFormat>,
 }
-#[derive(Debug, Clone, Deserialize, ToSchema)]
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct EventFirstLastQuery {
-    quantity: Option<u64>,
-    reverse: Option<bool>,
-    success: Option<bool>,
+    pub quantity: Option<u64>,
+    pub reverse: Option<bool>,
+    pub success: Option<bool>,
 }
 
+/// Interval at which subscriptions poll the node for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 use crate::doc::ApiDoc;
 use utoipa::OpenApi;
 use utoipa_rapidoc::RapiDoc;
@@ -63,10 +174,14 @@ use utoipa_rapidoc::RapiDoc;
 /// Allows sending an event request for a subject to the Kore node.
 /// These requests can be of any type of event (fact, creation, transfer, or end of life).
 /// In case of external invocation, the requests can be signed.
+/// Facts for inactive subjects and requests for schemas the governance does not define are
+/// rejected before reaching the node.
 ///
 /// # Parameters
 ///
 /// * `Extension(bridge): Extension<Arc<Bridge>>` - The Bridge extension wrapped in an `Arc`.
+/// * `Extension(cache): Extension<Arc<ResponseCache>>` - The response cache, from which what the request may change is dropped.
+/// * `headers: HeaderMap` - An `If-Match` header makes the request fail unless the subject is still at the tagged version.
 /// * `Json(request): Json<BridgeSignedEventRequest>` - The signed event request in JSON format.
 ///
 /// # Returns
@@ -86,15 +201,43 @@ use utoipa_rapidoc::RapiDoc;
                 "subject_id":"Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w"
             }
         )),
+        (status = 400, description = "The subject is not active, or the governance does not define its schema"),
+        (status = 412, description = "The subject has changed since the version given in If-Match"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn send_event_request(
     Extension(bridge): Extension<Arc<Bridge>>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
+    headers: HeaderMap,
     Json(request): Json<BridgeSignedEventRequest>,
 ) -> Result<Json<RequestData>, Error> {
+    let subject_id = match &request.request {
+        BridgeEventRequest::Create(_) => None,
+        BridgeEventRequest::Fact(x) => Some(&x.subject_id),
+        BridgeEventRequest::Transfer(x) => Some(&x.subject_id),
+        BridgeEventRequest::Confirm(x) => Some(&x.subject_id),
+        BridgeEventRequest::Reject(x) => Some(&x.subject_id),
+        BridgeEventRequest::EOL(x) => Some(&x.subject_id),
+    };
+    match subject_id {
+        Some(subject_id) => check_subject_version(&bridge, &headers, subject_id).await?,
+        None if headers.contains_key(header::IF_MATCH) => {
+            return Err(Error::PreconditionFailed(
+                "The subject of a create request has no version to match".to_owned(),
+            ));
+        }
+        None => {}
+    }
+
+    validate_event_request(&bridge, &cache, &request).await?;
+
+    let event_request = request.request.clone();
     match bridge.send_event_request(request).await {
-        Ok(response) => Ok(Json(RequestData::from(response))),
+        Ok(response) => {
+            cache.invalidate_request(&event_request).await;
+            Ok(Json(RequestData::from(response)))
+        }
         Err(e) => Err(Error::Kore(e.to_string())),
     }
 }
@@ -184,6 +327,7 @@ async fn get_approval(
 ///
 /// * `Extension(bridge): Extension<Arc<bridge>>` - The bridge extension wrapped in an `Arc`.
 /// * `Path(subject_id): Path<String>` -The identifier of the subject as a path parameter.
+/// * `headers: HeaderMap` - An `If-Match` header makes the vote fail unless the subject is still at the tagged version.
 /// * `Json(response): Json<String>` - The response (approval or rejection) in JSON format
 ///
 /// # Returns
@@ -203,14 +347,19 @@ async fn get_approval(
         example = json!(
             "The approval request for subject Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w has changed to RespondedAccepted"
         )),
+        (status = 400, description = "The subject is not active, or the governance does not define its schema"),
+        (status = 412, description = "The subject has changed since the version given in If-Match"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn patch_approval(
     Extension(bridge): Extension<Arc<Bridge>>,
     Path(subject_id): Path<String>,
+    headers: HeaderMap,
     Json(response): Json<String>,
 ) -> Result<Json<String>, Error> {
+    check_subject_version(&bridge, &headers, &subject_id).await?;
+
     match bridge.patch_approve(subject_id, response).await {
         Ok(response) => Ok(Json(response)),
         Err(e) => Err(Error::Kore(e.to_string())),
@@ -489,7 +638,11 @@ async fn manual_distribution(
 
 /// All Governances
 ///
-/// Gets all the governorships to which the node belongs
+/// Gets all the governorships to which the node belongs.
+/// The list can be searched by name and description, sorted and paginated; the total number of
+/// matching governances is returned in the `X-Total-Count` header.
+/// Sorting by sn requires reading the state of every listed governance, so it is slower and only
+/// allowed when the search leaves at most 500 governances.
 ///
 /// # Parameters
 ///
@@ -498,7 +651,7 @@ async fn manual_distribution(
 ///
 /// # Returns
 ///
-/// * `Result<Json<Vec<GovsData>>, Error>` - A JSON with governance information or an error if the request fails.
+/// * `Result<(HeaderMap, Json<Vec<GovsData>>), Error>` - A JSON with governance information or an error if the request fails.
 #[ utoipa::path(
     get,
     path = "/register-governances",
@@ -509,6 +662,9 @@ async fn manual_distribution(
     ),
     responses(
         (status = 200, description = "Gets all the governorships to which the node belongs", body = [GovsData],
+        headers(
+            ("X-Total-Count" = u64, description = "Number of governances matching the filters")
+        ),
         example = json!(
             [
                 {
@@ -521,25 +677,58 @@ async fn manual_distribution(
                 }
             ]
         )),
+        (status = 400, description = "Governances can not be sorted by schema, or too many governances to sort by sn"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn get_all_govs(
     Extension(bridge): Extension<Arc<Bridge>>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
     Query(parameters): Query<GovQuery>,
-) -> Result<Json<Vec<GovsData>>, Error> {
-    match bridge.get_all_govs(parameters.active).await {
-        Ok(response) => Ok(Json(
-            response.iter().map(|x| GovsData::from(x.clone())).collect(),
-        )),
-        Err(e) => Err(Error::Kore(e.to_string())),
+) -> Result<(HeaderMap, Json<Vec<GovsData>>), Error> {
+    let mut govs = cache.governances(&bridge, parameters.active).await?;
+
+    if let Some(search) = &parameters.search {
+        govs.retain(|x| matches_search(&x.name, &x.description, search));
+    }
+
+    match parameters.sort {
+        Some(SortField::Name) => govs.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SortField::Sn) => {
+            let details = get_subject_details(
+                &bridge,
+                govs.iter().map(|x| x.governance_id.clone()).collect(),
+            )
+            .await?;
+            govs.sort_by_key(|x| details.get(&x.governance_id).map(|x| x.sn));
+        }
+        Some(SortField::Schema) => {
+            return Err(Error::BadRequest(
+                "Governances can not be sorted by schema".to_owned(),
+            ));
+        }
+        None => {}
     }
+
+    if parameters.reverse.unwrap_or_default() {
+        govs.reverse();
+    }
+
+    let headers = total_count(govs.len());
+    Ok((
+        headers,
+        Json(page(govs, parameters.quantity, parameters.page)),
+    ))
 }
 
 /// All Subjects
 ///
 /// Allows obtaining the list of subjects known by the node with pagination.
 /// It can also be used to obtain only the governances and all subjects belonging to a specific governance.
+/// The list can be searched by name and description, filtered by namespace prefix and sorted; the total
+/// number of matching subjects is returned in the `X-Total-Count` header.
+/// Filtering by namespace or sorting by sn requires reading the state of every listed subject, so it is
+/// slower and only allowed when the other filters leave at most 500 subjects.
 ///
 /// # Parameters
 ///
@@ -549,7 +738,7 @@ async fn get_all_govs(
 ///
 /// # Returns
 ///
-/// * `Result<Json<Vec<RegisterData>>, Error>` - A list of subjects in JSON format or an error if the request fails.
+/// * `Result<(HeaderMap, Json<Vec<RegisterData>>), Error>` - A list of subjects in JSON format or an error if the request fails.
 #[  utoipa::path(
     get,
     path = "/register-subjects/{governance_id}",
@@ -561,6 +750,9 @@ async fn get_all_govs(
     ),
     responses(
         (status = 200, description = "Subjects Data successfully retrieved", body = [RegisterDataSubj],
+        headers(
+            ("X-Total-Count" = u64, description = "Number of subjects matching the filters")
+        ),
         example = json!(
             [
                 {
@@ -570,41 +762,78 @@ async fn get_all_govs(
                 }
             ]
         )),
+        (status = 400, description = "Too many subjects to filter by namespace or sort by sn"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn get_all_subjects(
     Extension(bridge): Extension<Arc<Bridge>>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
     Path(governance_id): Path<String>,
     Query(parameters): Query<SubjectQuery>,
-) -> Result<Json<Vec<RegisterDataSubj>>, Error> {
-    match bridge
-        .get_all_subjs(governance_id, parameters.active, parameters.schema)
-        .await
-    {
-        Ok(response) => Ok(Json(
-            response
-                .iter()
-                .map(|x| RegisterDataSubj::from(x.clone()))
-                .collect(),
-        )),
-        Err(e) => Err(Error::Kore(e.to_string())),
+) -> Result<(HeaderMap, Json<Vec<RegisterDataSubj>>), Error> {
+    let mut subjects = cache
+        .subjects(&bridge, governance_id, parameters.active, parameters.schema)
+        .await?;
+
+    if let Some(search) = &parameters.search {
+        subjects.retain(|x| matches_search(&x.name, &x.description, search));
+    }
+
+    let sort_by_sn = parameters.sort == Some(SortField::Sn);
+    if parameters.namespace.is_some() || sort_by_sn {
+        let details = get_subject_details(
+            &bridge,
+            subjects.iter().map(|x| x.subject_id.clone()).collect(),
+        )
+        .await?;
+
+        if let Some(prefix) = &parameters.namespace {
+            subjects.retain(|x| {
+                details
+                    .get(&x.subject_id)
+                    .is_some_and(|x| matches_namespace(&x.namespace, prefix))
+            });
+        }
+        if sort_by_sn {
+            subjects.sort_by_key(|x| details.get(&x.subject_id).map(|x| x.sn));
+        }
+    }
+
+    match parameters.sort {
+        Some(SortField::Name) => subjects.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SortField::Schema) => subjects.sort_by(|a, b| a.schema.cmp(&b.schema)),
+        Some(SortField::Sn) | None => {}
+    }
+
+    if parameters.reverse.unwrap_or_default() {
+        subjects.reverse();
     }
+
+    let headers = total_count(subjects.len());
+    Ok((
+        headers,
+        Json(page(subjects, parameters.quantity, parameters.page)),
+    ))
 }
 
 /// Subject Events
 ///
 /// Allows obtaining specific events of a subject by its identifier.
+/// Pages are numbered with `quantity` and `page`, unless a cursor, `after_sn`, `before_sn`, `limit`,
+/// `event_type` or `success` is given: then events are paginated with cursors keyed by sn, which
+/// remain stable as new events arrive, and the cursors to the adjacent pages are also returned in a
+/// `Link` header.
 ///
 /// # Parameters
 ///
 /// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
 /// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
-/// * `Query(parameters): Query<EventsQuery>` - The pagination parameters for the request.
+/// * `Query(parameters): Query<EventsQuery>` - The pagination and filter parameters for the request.
 ///
 /// # Returns
 ///
-/// * `Result<Json<PaginatorEvents>, Error>` - A list of events in JSON format or an error if the request fails.
+/// * `Result<(HeaderMap, Json<EventsPage>), Error>` - A list of events in JSON format or an error if the request fails.
 #[ utoipa::path(
     get,
     path = "/events/{subject_id}",
@@ -615,7 +844,10 @@ async fn get_all_subjects(
         ("parameters" = EventsQuery, Query, description = "The query parameters for the request"),
     ),
     responses(
-        (status = 200, description = "Allows obtaining specific events of a subject by its identifier.", body = [PaginatorEvents],
+        (status = 200, description = "A page of events of the subject, numbered or with cursors", body = EventsPage,
+        headers(
+            ("Link" = String, description = "Links to the next and previous pages, with cursors")
+        ),
         example = json!(
             {
                 "events": [
@@ -641,6 +873,7 @@ async fn get_all_subjects(
                 }
             }
         )),
+        (status = 400, description = "Invalid cursor"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
@@ -648,28 +881,104 @@ async fn get_events(
     Extension(bridge): Extension<Arc<Bridge>>,
     Path(subject_id): Path<String>,
     Query(parameters): Query<EventsQuery>,
-) -> Result<Json<PaginatorEvents>, Error> {
+) -> Result<(HeaderMap, Json<EventsPage>), Error> {
+    if let Some(cursor) = parameters.cursor() {
+        let (headers, Json(page)) = events_cursor(
+            &bridge,
+            &format!("{}/events/{}", API_PREFIX, subject_id),
+            &subject_id,
+            cursor,
+        )
+        .await?;
+        return Ok((headers, Json(EventsPage::Cursor(page))));
+    }
+
     match bridge
         .get_events(subject_id, parameters.quantity, parameters.page)
         .await
     {
-        Ok(response) => Ok(Json(PaginatorEvents::from(response))),
+        Ok(response) => Ok((
+            HeaderMap::new(),
+            Json(EventsPage::Numbered(PaginatorEvents::from(response))),
+        )),
         Err(e) => Err(Error::Kore(e.to_string())),
     }
 }
 
+/// Builds a page of events of a subject, with the links to the adjacent pages relative to `path`.
+async fn events_cursor(
+    bridge: &Bridge,
+    path: &str,
+    subject_id: &str,
+    parameters: EventsCursorQuery,
+) -> Result<(HeaderMap, Json<CursorEvents>), Error> {
+    let (after, before) = match &parameters.cursor {
+        Some(cursor) => match PageCursor::decode(cursor)? {
+            PageCursor::After(sn) => (Some(sn), None),
+            PageCursor::Before(sn) => (None, Some(sn)),
+        },
+        None => (parameters.after_sn, parameters.before_sn),
+    };
+    let limit = limit(parameters.limit);
+
+    let (events, next, prev) = get_events_page(
+        bridge,
+        subject_id,
+        after,
+        before,
+        limit,
+        parameters.event_type,
+        parameters.success,
+    )
+    .await?;
+
+    let url = |cursor: PageCursor| {
+        let query = EventsCursorQuery {
+            cursor: Some(cursor.encode()),
+            limit: Some(limit),
+            event_type: parameters.event_type,
+            success: parameters.success,
+            ..Default::default()
+        };
+        page_url(path, &query)
+    };
+
+    let mut headers = HeaderMap::new();
+    if let Some(link) =
+        link_header(next.map(url), prev.map(url)).and_then(|link| HeaderValue::from_str(&link).ok())
+    {
+        headers.insert(header::LINK, link);
+    }
+
+    Ok((
+        headers,
+        Json(CursorEvents {
+            events,
+            next: next.map(|x| x.encode()),
+            prev: prev.map(|x| x.encode()),
+        }),
+    ))
+}
+
 /// Subject State
 ///
 /// Allows obtaining specific state of a subject by its identifier.
+/// If a sn is given, the state at that event is rebuilt by replaying the events from genesis, up to 10000 of
+/// them, and returned along with a hash of the rebuilt properties.
+/// The properties can be narrowed with a comma-separated list of JSON pointers in `fields`, which returns
+/// an object keyed by pointer, or with a JSONPath expression in `query`, which returns the matching values.
+/// The state is tagged with its subject and sn; the state at a given sn never changes and may be cached for good.
 ///
 /// # Parameters
 ///
 /// * `Extension(bridge): Extension<Arc<Bridge>>` - bridge extension wrapped in an `Arc`.
 /// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<StateQuery>` - The query parameters for the request.
+/// * `headers: HeaderMap` - An `If-None-Match` header with the current tag returns a 304 without body.
 ///
 /// # Returns
 ///
-/// * `Result<Json<SubjectInfo>, Error>` -the state of the subject in JSON format or an error if the request fails.
+/// * `Result<Response, Error>` -the state of the subject in JSON format or an error if the request fails.
 #[utoipa::path(
     get,
     path = "/state/{subject_id}",
@@ -677,9 +986,10 @@ async fn get_events(
     tag = "State",
     params(
         ("subject_id" = String, Path, description =  "Subject unique id"),
+        ("parameters" = StateQuery, Query, description = "The query parameters for the request"),
     ),
     responses(
-        (status = 200, description = "Allows obtaining specific state of a subject by its identifier.", body = [SubjectInfo],
+        (status = 200, description = "Allows obtaining specific state of a subject by its identifier. A SubjectInfo is returned without sn, a SubjectState when a sn is given.", body = StateResponse,
         example = json!(
             {
                 "active": true,
@@ -771,17 +1081,163 @@ async fn get_events(
                 "subject_id": "Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w"
             }
         )),
+        (status = 304, description = "The state has not changed since the tag given in If-None-Match"),
+        (status = 400, description = "Invalid sn, fields or query"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn get_state(
     Extension(bridge): Extension<Arc<Bridge>>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
     Path(subject_id): Path<String>,
-) -> Result<Json<SubjectInfo>, Error> {
-    match bridge.get_subject(subject_id).await {
-        Ok(response) => Ok(Json(SubjectInfo::from(response))),
-        Err(e) => Err(Error::Kore(e.to_string())),
+    Query(parameters): Query<StateQuery>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let variant = state_variant(&parameters);
+    let mut subject = cache.subject(&bridge, subject_id.clone()).await?;
+
+    let Some(sn) = parameters.sn else {
+        let tag = entity_tag(&subject_id, subject.sn, variant.as_deref());
+        if none_match(&headers, &tag) {
+            return Ok(not_modified(&tag, false));
+        }
+
+        subject.properties = select_properties(subject.properties, &parameters)?;
+        return Ok(tagged(Json(StateResponse::Current(subject)), &tag, false));
+    };
+
+    if sn > subject.sn {
+        return Err(Error::BadRequest(format!(
+            "The subject {} has no event with sn {}",
+            subject_id, sn
+        )));
+    }
+
+    // A past state never changes, so its tag is checked before replaying the events.
+    let tag = entity_tag(&subject_id, sn, variant.as_deref());
+    if none_match(&headers, &tag) {
+        return Ok(not_modified(&tag, true));
+    }
+
+    let events = get_events_until(&bridge, &subject_id, sn).await?;
+    let properties = replay(&events, subject.schema_id == "governance").map_err(Error::Kore)?;
+
+    Ok(tagged(
+        Json(StateResponse::Past(SubjectState {
+            subject_id,
+            governance_id: subject.governance_id,
+            schema_id: subject.schema_id,
+            sn,
+            properties_hash: properties_hash(&properties),
+            properties: select_properties(properties, &parameters)?,
+        })),
+        &tag,
+        true,
+    ))
+}
+
+/// Variant of the entity tag of a state narrowed with `fields` or `query`.
+fn state_variant(parameters: &StateQuery) -> Option<String> {
+    match (&parameters.fields, &parameters.query) {
+        (None, None) => None,
+        (fields, query) => Some(format!("{:?}:{:?}", fields, query)),
+    }
+}
+
+pub(crate) fn select_properties(
+    properties: Value,
+    parameters: &StateQuery,
+) -> Result<Value, Error> {
+    match (&parameters.fields, &parameters.query) {
+        (Some(_), Some(_)) => Err(Error::BadRequest(
+            "fields and query can not be used at the same time".to_owned(),
+        )),
+        (Some(fields), None) => project(&properties, fields).map_err(Error::BadRequest),
+        (None, Some(expression)) => query(&properties, expression).map_err(Error::BadRequest),
+        (None, None) => Ok(properties),
+    }
+}
+
+/// Subject State Diff
+///
+/// Allows obtaining the changes in the state of a subject between two sequence numbers.
+/// Returns the combined JSON Patch and the change of each field with the events that touched it.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<DiffQuery>` - The query parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<Json<StateDiff>, Error>` - The changes in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/state/{subject_id}/diff",
+    operation_id = "Subject State Diff",
+    tag = "State",
+    params(
+        ("subject_id" = String, Path, description =  "Subject unique id"),
+        ("parameters" = DiffQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The changes in the state of the subject", body = StateDiff,
+        example = json!(
+            {
+                "subject_id": "JukqvNApVZMlEBI5DrZlZWEUgZs9vdEC6MEmmAQpwmns",
+                "from": 1,
+                "to": 3,
+                "patch": [
+                    {
+                        "op": "replace",
+                        "path": "/one",
+                        "value": 200
+                    }
+                ],
+                "changes": [
+                    {
+                        "path": "/one",
+                        "from": 100,
+                        "to": 200,
+                        "events": [2, 3]
+                    }
+                ]
+            }
+        )),
+        (status = 400, description = "Invalid range of sequence numbers, or too many events to replay"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_state_diff(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(subject_id): Path<String>,
+    Query(parameters): Query<DiffQuery>,
+) -> Result<Json<StateDiff>, Error> {
+    let subject = match bridge.get_subject(subject_id.clone()).await {
+        Ok(subject) => subject,
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    if parameters.from > parameters.to || parameters.to > subject.sn {
+        return Err(Error::BadRequest(format!(
+            "Invalid range of sequence numbers {}..{}, the last sn of the subject is {}",
+            parameters.from, parameters.to, subject.sn
+        )));
     }
+
+    let events = get_events_until(&bridge, &subject_id, parameters.to).await?;
+    let governance = subject.schema_id == "governance";
+    let (patch, changes) =
+        diff(&events, parameters.from, parameters.to, governance).map_err(Error::Kore)?;
+
+    Ok(Json(StateDiff {
+        subject_id,
+        from: parameters.from,
+        to: parameters.to,
+        patch,
+        changes,
+    }))
 }
 
 /// Subject Signatures
@@ -792,10 +1248,11 @@ async fn get_state(
 ///
 /// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
 /// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `headers: HeaderMap` - An `If-None-Match` header with the current tag returns a 304 without body.
 ///
 /// # Returns
 ///
-/// * `Result<Json<SignaturesInfo>, Error>` - the signature in JSON format or an error if the request fails.
+/// * `Result<Response, Error>` - the signature in JSON format or an error if the request fails.
 #[ utoipa::path(
     get,
     path = "/signatures/{subject_id}",
@@ -824,17 +1281,139 @@ async fn get_state(
                 "subject_id": "Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w"
             }
         )),
+        (status = 304, description = "The signatures have not changed since the tag given in If-None-Match"),
         (status = 500, description = "Internal Server Error"),
     )
 )]
 async fn get_signatures(
     Extension(bridge): Extension<Arc<Bridge>>,
     Path(subject_id): Path<String>,
-) -> Result<Json<SignaturesInfo>, Error> {
-    match bridge.get_signatures(subject_id).await {
-        Ok(response) => Ok(Json(SignaturesInfo::from(response))),
-        Err(e) => Err(Error::Kore(e.to_string())),
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let signatures = match bridge.get_signatures(subject_id).await {
+        Ok(response) => SignaturesInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    let tag = entity_tag(
+        &signatures.subject_id,
+        signatures.sn,
+        Some(&signers(&signatures)),
+    );
+    if none_match(&headers, &tag) {
+        Ok(not_modified(&tag, false))
+    } else {
+        Ok(tagged(Json(signatures), &tag, false))
+    }
+}
+
+/// Signers of each protocol, sorted, so that the tag of the signatures of an event changes when
+/// a new signature arrives for it.
+fn signers(signatures: &SignaturesInfo) -> String {
+    let protocols = [
+        ("eval", signatures.signatures_eval.as_ref()),
+        ("appr", signatures.signatures_appr.as_ref()),
+        ("vali", Some(&signatures.signatures_vali)),
+    ];
+
+    let mut signers: Vec<String> = protocols
+        .into_iter()
+        .flat_map(|(protocol, signatures)| {
+            signatures.into_iter().flatten().map(move |x| match x {
+                ProtocolsSignaturesInfo::Signature(x) => format!("{}:{}", protocol, x.signer),
+                ProtocolsSignaturesInfo::TimeOut(x) => format!("{}:{}:timeout", protocol, x.who),
+            })
+        })
+        .collect();
+    signers.sort_unstable();
+    signers.join(",")
+}
+
+/// Subject Signatures Quorum
+///
+/// Resolves the signatures of an event against the roles of the governance and shows, for each protocol,
+/// the eligible members, whether the quorum was reached and the members that timed out.
+/// The node only stores the signatures of the last event, so a sn other than the last one can not be inspected.
+/// The roles are those of the governance version in force when the event was signed.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<SignaturesQuery>` - The query parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<Json<SignaturesQuorum>, Error>` - The quorum analysis in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/signatures/{subject_id}/quorum",
+    operation_id = "Subject Signatures Quorum",
+    tag = "Signature",
+    params(
+        ("subject_id" = String, Path, description =  "Subject unique id"),
+        ("parameters" = SignaturesQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The quorum analysis of the signatures", body = SignaturesQuorum,
+        example = json!(
+            {
+                "subject_id": "Jd_vA5Dl1epomG7wyeHiqgKdOIBi28vNgHjRl6hy1N5w",
+                "sn": 0,
+                "governance_version": 0,
+                "evaluation": null,
+                "approval": null,
+                "validation": {
+                    "quorum": "MAJORITY",
+                    "open": false,
+                    "eligible": [
+                        "E2ZY7GjU14U3m-iAqvhQM6kiG62uqLdBMBwv4J-4tzwI"
+                    ],
+                    "required": 1,
+                    "reached": true,
+                    "signers": [
+                        {
+                            "signer": "E2ZY7GjU14U3m-iAqvhQM6kiG62uqLdBMBwv4J-4tzwI",
+                            "name": "Owner",
+                            "eligible": true,
+                            "timestamp": 17346911
+                        }
+                    ],
+                    "timed_out": []
+                }
+            }
+        )),
+        (status = 400, description = "The signatures of the given sn or the governance version in force when they were made are not available"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_signatures_quorum(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(subject_id): Path<String>,
+    Query(parameters): Query<SignaturesQuery>,
+) -> Result<Json<SignaturesQuorum>, Error> {
+    let subject = match bridge.get_subject(subject_id.clone()).await {
+        Ok(response) => SubjectInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    let signatures = match bridge.get_signatures(subject_id).await {
+        Ok(response) => SignaturesInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    if let Some(sn) = parameters.sn.filter(|sn| *sn != signatures.sn) {
+        return Err(Error::BadRequest(format!(
+            "The node only keeps the signatures of the last event (sn {}), not of sn {}",
+            signatures.sn, sn
+        )));
     }
+
+    let governance = get_signing_governance(&bridge, &subject, &signatures).await?;
+
+    analyze(&governance, &subject, signatures)
+        .map(Json)
+        .map_err(Error::Kore)
 }
 
 /// Controller-id
@@ -1056,12 +1635,14 @@ async fn get_keys(Extension(bridge): Extension<Arc<Bridge>>) -> impl IntoRespons
 /// # Parameters
 ///
 /// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Extension(cache): Extension<Arc<ResponseCache>>` - The response cache wrapped in an `Arc`.
 /// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
 /// * `Query(parameters): Query<EventSnQuery>` - The query parameters for the request.
+/// * `headers: HeaderMap` - An `If-None-Match` header with the tag of the event returns a 304 without body.
 ///
 /// # Returns
 ///
-/// * `Result<Json<EventInfo>, Error>` - A list of events in JSON format or an error if the request fails.
+/// * `Result<Response, Error>` - A list of events in JSON format or an error if the request fails.
 #[utoipa::path(
     get,
     path = "/event/{subject_id}",
@@ -1103,15 +1684,44 @@ async fn get_keys(Extension(bridge): Extension<Arc<Bridge>>) -> impl IntoRespons
 )]
 async fn get_event_sn(
     Extension(bridge): Extension<Arc<Bridge>>,
+    Extension(cache): Extension<Arc<ResponseCache>>,
     Path(subject_id): Path<String>,
     Query(parameters): Query<EventSnQuery>,
-) -> Result<Json<EventInfo>, Error> {
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    // Events never change, so the tag of an event the subject already has is answered without
+    // asking for it.
+    let tag = entity_tag(&subject_id, parameters.sn, None);
+    if none_match(&headers, &tag)
+        && cache.subject(&bridge, subject_id.clone()).await?.sn >= parameters.sn
+    {
+        return Ok(not_modified(&tag, true));
+    }
+
     match bridge.get_event_sn(subject_id, parameters.sn).await {
-        Ok(response) => Ok(Json(EventInfo::from(response))),
+        Ok(response) => Ok(tagged(Json(EventInfo::from(response)), &tag, true)),
         Err(e) => Err(Error::Kore(e.to_string())),
     }
 }
 
+/// Checks the `If-Match` header of a write on a subject against the current version of the
+/// subject, which is only asked to the node when the header is present.
+async fn check_subject_version(
+    bridge: &Bridge,
+    headers: &HeaderMap,
+    subject_id: &str,
+) -> Result<(), Error> {
+    if !headers.contains_key(header::IF_MATCH) {
+        return Ok(());
+    }
+
+    let sn = match bridge.get_subject(subject_id.to_owned()).await {
+        Ok(subject) => subject.sn,
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+    check_if_match(headers, subject_id, Some(sn))
+}
+
 /// First or End Events
 ///
 /// Given a subject id a specific number of events can be obtained, depending on the quantity, reverse and success parameters.
@@ -1224,45 +1834,1648 @@ async fn get_pending_transfers(
     }
 }
 
-pub fn build_routes(bridge: Bridge) -> Router {
-    let bridge = Arc::new(bridge);
-    let routes = Router::new()
-        .route("/signatures/{subject_id}", get(get_signatures))
-        .route("/state/{subject_id}", get(get_state))
-        .route("/events/{subject_id}", get(get_events))
-        .route("/event/{subject_id}", get(get_event_sn))
-        .route(
-            "/events-first-last/{subject_id}",
-            get(get_first_or_end_events),
-        )
-        .route("/register-subjects/{governance_id}", get(get_all_subjects))
-        .route("/register-governances", get(get_all_govs))
-        .route("/update/{subject_id}", post(update_subject))
-        .route("/check-transfer/{subject_id}", post(check_transfer))
-        .route(
-            "/manual-distribution/{subject_id}",
-            post(manual_distribution),
-        )
-        .route("/auth/{subject_id}", delete(delete_auth_subject))
-        .route("/auth/{subject_id}", get(get_witnesses_subject))
-        .route("/auth", get(get_all_auth_subjects))
-        .route("/auth/{subject_id}", put(put_auth))
-        .route("/approval-request/{subject_id}", patch(patch_approval))
-        .route("/approval-request/{subject_id}", get(get_approval))
-        .route("/event-request/{request_id}", get(get_request_state))
-        .route("/event-request", post(send_event_request))
-        .route("/controller-id", get(get_controller_id))
-        .route("/peer-id", get(get_peer_id))
-        .route("/config", get(get_config))
-        .route("/keys", get(get_keys))
-        .route("/pending-transfers", get(get_pending_transfers))
-        .layer(ServiceBuilder::new().layer(Extension(bridge)));
+/// Reads the events of a subject with sn in the batch `batch` of [`EVENTS_BATCH`] events, that
+/// is from `batch * EVENTS_BATCH` to the next batch, in a single call to the node.
+pub(crate) async fn get_events_batch(
+    bridge: &Bridge,
+    subject_id: &str,
+    batch: u64,
+) -> Result<Vec<EventInfo>, Error> {
+    match bridge
+        .get_events(subject_id.to_owned(), Some(EVENTS_BATCH), Some(batch + 1))
+        .await
+    {
+        Ok(response) => Ok(PaginatorEvents::from(response).events),
+        Err(e) => Err(Error::Kore(e.to_string())),
+    }
+}
 
-    if build_doc() {
-        Router::new()
-            .merge(routes)
-            .merge(RapiDoc::with_openapi("/doc/koreapi.json", ApiDoc::openapi()).path("/doc"))
+/// Reads a page of events of a subject between two sequence numbers, both exclusive.
+///
+/// Reads backwards from `before` when only it is given, and forwards from `after` otherwise.
+/// Events are read from the node in batches and at most [`MAX_SCAN`] of them are examined; the
+/// returned cursors point to the adjacent pages.
+pub(crate) async fn get_events_page(
+    bridge: &Bridge,
+    subject_id: &str,
+    after: Option<u64>,
+    before: Option<u64>,
+    limit: u64,
+    event_type: Option<EventType>,
+    success: Option<bool>,
+) -> Result<(Vec<EventInfo>, Option<PageCursor>, Option<PageCursor>), Error> {
+    let last_sn = match bridge.get_subject(subject_id.to_owned()).await {
+        Ok(subject) => subject.sn,
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    let lower = after.map_or(0, |sn| sn.saturating_add(1));
+    let upper = match before {
+        Some(0) => None,
+        Some(sn) => Some((sn - 1).min(last_sn)),
+        None => Some(last_sn),
+    }
+    .filter(|upper| lower <= *upper);
+
+    let mut events = vec![];
+    let mut next = None;
+    let mut prev = None;
+
+    let Some(upper) = upper else {
+        return Ok((events, next, prev));
+    };
+
+    let backwards = before.is_some() && after.is_none();
+    let batches = lower / EVENTS_BATCH..=upper / EVENTS_BATCH;
+    let batches: Box<dyn Iterator<Item = u64> + Send> = if backwards {
+        Box::new(batches.rev())
     } else {
-        Router::new().merge(routes)
+        Box::new(batches)
+    };
+
+    let mut scanned = None;
+    let mut examined = 0;
+    'batches: for batch in batches {
+        let mut batch = get_events_batch(bridge, subject_id, batch).await?;
+        batch.retain(|x| (lower..=upper).contains(&x.sn));
+        if backwards {
+            batch.reverse();
+        }
+
+        for event in batch {
+            scanned = Some(event.sn);
+            examined += 1;
+
+            let matches = event_type.is_none_or(|x| EventType::from(&event.event_req) == x)
+                && success.is_none_or(|x| event.succes == x);
+            if matches {
+                events.push(event);
+                if events.len() as u64 == limit {
+                    break 'batches;
+                }
+            }
+            if examined == MAX_SCAN {
+                break 'batches;
+            }
+        }
     }
+
+    if let Some(scanned) = scanned {
+        if backwards {
+            events.reverse();
+            if scanned > lower {
+                prev = Some(PageCursor::Before(scanned));
+            }
+            if upper < last_sn {
+                next = Some(PageCursor::After(upper));
+            }
+        } else {
+            if scanned < upper {
+                next = Some(PageCursor::After(scanned));
+            }
+            if lower > 0 {
+                prev = Some(PageCursor::Before(lower));
+            }
+        }
+    }
+
+    Ok((events, next, prev))
+}
+
+/// Reads the events of a subject from genesis up to `sn`, in batches, to replay them.
+/// At most [`MAX_REPLAY`] events are read.
+async fn get_events_until(
+    bridge: &Bridge,
+    subject_id: &str,
+    sn: u64,
+) -> Result<Vec<EventInfo>, Error> {
+    if sn >= MAX_REPLAY {
+        return Err(Error::BadRequest(format!(
+            "Only the states of the first {} events of a subject can be rebuilt",
+            MAX_REPLAY
+        )));
+    }
+
+    let mut events = vec![];
+    for batch in 0..=sn / EVENTS_BATCH {
+        let batch = get_events_batch(bridge, subject_id, batch).await?;
+        events.extend(batch.into_iter().filter(|x| x.sn <= sn));
+    }
+    Ok(events)
+}
+
+/// Reads the state of each of the listed subjects, for the filters and sorts that the listings
+/// of the node can not apply. At most [`MAX_DETAILS`] subjects are read, a few at a time.
+async fn get_subject_details(
+    bridge: &Bridge,
+    subject_ids: Vec<String>,
+) -> Result<HashMap<String, SubjectInfo>, Error> {
+    if subject_ids.len() as u64 > MAX_DETAILS {
+        return Err(Error::BadRequest(format!(
+            "Filtering by namespace or sorting by sn reads every listed subject, narrow the \
+             listing to at most {} subjects with schema or search",
+            MAX_DETAILS
+        )));
+    }
+
+    stream::iter(subject_ids)
+        .map(|subject_id| async move {
+            match bridge.get_subject(subject_id.clone()).await {
+                Ok(subject) => Ok((subject_id, SubjectInfo::from(subject))),
+                Err(e) => Err(Error::Kore(e.to_string())),
+            }
+        })
+        .buffer_unordered(DETAILS_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+pub(crate) async fn get_governance(
+    bridge: &Bridge,
+    governance_id: String,
+) -> Result<Governance, Error> {
+    let subject = match bridge.get_subject(governance_id.clone()).await {
+        Ok(subject) => subject,
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    if subject.schema_id != "governance" {
+        return Err(Error::BadRequest(format!(
+            "Subject {} is not a governance",
+            governance_id
+        )));
+    }
+
+    Governance::try_from(subject.properties)
+        .map_err(|e| Error::Kore(format!("Can not parse governance: {}", e)))
+}
+
+async fn get_subject_governance(
+    bridge: &Bridge,
+    subject: &SubjectInfo,
+) -> Result<Governance, Error> {
+    if subject.schema_id == "governance" {
+        Governance::try_from(subject.properties.clone())
+            .map_err(|e| Error::Kore(format!("Can not parse governance: {}", e)))
+    } else {
+        get_governance(bridge, subject.governance_id.clone()).await
+    }
+}
+
+/// Gets the governance in force when the event of the signatures was signed. An event of a
+/// governance is signed under the version before it, which is rebuilt from its events. Only the
+/// current version of the governance of other subjects can be read, so it is used when its last
+/// event was signed before the event of the subject.
+async fn get_signing_governance(
+    bridge: &Bridge,
+    subject: &SubjectInfo,
+    signatures: &SignaturesInfo,
+) -> Result<Governance, Error> {
+    if subject.schema_id == "governance" {
+        let events =
+            get_events_until(bridge, &subject.subject_id, signatures.sn.saturating_sub(1)).await?;
+        let state = replay(&events, true).map_err(Error::Kore)?;
+        return Governance::try_from(state)
+            .map_err(|e| Error::Kore(format!("Can not parse governance: {}", e)));
+    }
+
+    let governance = get_governance(bridge, subject.governance_id.clone()).await?;
+    if governance.version == 0 {
+        return Ok(governance);
+    }
+
+    let changed = match bridge.get_signatures(subject.governance_id.clone()).await {
+        Ok(response) => SignaturesInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    match (
+        signature_timestamps(&changed).max(),
+        signature_timestamps(signatures).min(),
+    ) {
+        (Some(changed), Some(signed)) if changed < signed => Ok(governance),
+        _ => Err(Error::BadRequest(format!(
+            "The governance changed to version {} after the event was signed, and the version \
+             in force then can not be read",
+            governance.version
+        ))),
+    }
+}
+
+fn signature_timestamps(signatures: &SignaturesInfo) -> impl Iterator<Item = u64> + '_ {
+    signatures
+        .signatures_eval
+        .iter()
+        .chain(signatures.signatures_appr.iter())
+        .chain([&signatures.signatures_vali])
+        .flatten()
+        .filter_map(|signature| match signature {
+            ProtocolsSignaturesInfo::Signature(signature) => Some(signature.timestamp),
+            ProtocolsSignaturesInfo::TimeOut(_) => None,
+        })
+}
+
+/// Governance Members
+///
+/// Gets the members of a governance given its identifier.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<Member>>, Error>` - A list of members in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/members",
+    operation_id = "Governance Members",
+    tag = "Governance",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+    ),
+    responses(
+        (status = 200, description = "Members of the governance", body = [Member],
+        example = json!(
+            [
+                {
+                    "id": "E2ZY7GjU14U3m-iAqvhQM6kiG62uqLdBMBwv4J-4tzwI",
+                    "name": "Owner"
+                }
+            ]
+        )),
+        (status = 400, description = "The subject is not a governance"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governance_members(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(governance_id): Path<String>,
+) -> Result<Json<Vec<Member>>, Error> {
+    let governance = get_governance(&bridge, governance_id).await?;
+    Ok(Json(governance.members))
+}
+
+/// Governance Roles
+///
+/// Gets the roles of a governance given its identifier, optionally filtered by role and schema.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+/// * `Query(parameters): Query<RoleQuery>` - The query parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<Role>>, Error>` - A list of roles in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/roles",
+    operation_id = "Governance Roles",
+    tag = "Governance",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+        ("parameters" = RoleQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "Roles of the governance", body = [Role],
+        example = json!(
+            [
+                {
+                    "namespace": "",
+                    "role": "EVALUATOR",
+                    "schema": "ALL",
+                    "who": {
+                        "NAME": "Owner"
+                    }
+                }
+            ]
+        )),
+        (status = 400, description = "The subject is not a governance"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governance_roles(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(governance_id): Path<String>,
+    Query(parameters): Query<RoleQuery>,
+) -> Result<Json<Vec<Role>>, Error> {
+    let governance = get_governance(&bridge, governance_id).await?;
+    Ok(Json(
+        governance
+            .roles
+            .into_iter()
+            .filter(|x| parameters.role.is_none_or(|role| x.role == role))
+            .filter(|x| {
+                parameters
+                    .schema
+                    .as_ref()
+                    .is_none_or(|schema| x.schema.applies_to(schema))
+            })
+            .collect(),
+    ))
+}
+
+/// Governance Schemas
+///
+/// Gets the schemas defined by a governance given its identifier.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<Schema>>, Error>` - A list of schemas in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/schemas",
+    operation_id = "Governance Schemas",
+    tag = "Governance",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+    ),
+    responses(
+        (status = 200, description = "Schemas of the governance", body = [Schema],
+        example = json!(
+            [
+                {
+                    "id": "Test",
+                    "initial_value": {
+                        "value": 0
+                    },
+                    "contract": {
+                        "raw": "dXNlIHNlcmRlOjp7U2VyaWFsaXplLCBEZXNlcmlhbGl6ZX07"
+                    }
+                }
+            ]
+        )),
+        (status = 400, description = "The subject is not a governance"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governance_schemas(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(governance_id): Path<String>,
+) -> Result<Json<Vec<Schema>>, Error> {
+    let governance = get_governance(&bridge, governance_id).await?;
+    Ok(Json(governance.schemas))
+}
+
+/// Governance Policies
+///
+/// Gets the quorum policies of a governance given its identifier.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<Policy>>, Error>` - A list of policies in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/policies",
+    operation_id = "Governance Policies",
+    tag = "Governance",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+    ),
+    responses(
+        (status = 200, description = "Policies of the governance", body = [Policy],
+        example = json!(
+            [
+                {
+                    "id": "governance",
+                    "approve": {
+                        "quorum": "MAJORITY"
+                    },
+                    "evaluate": {
+                        "quorum": "MAJORITY"
+                    },
+                    "validate": {
+                        "quorum": {
+                            "FIXED": 1
+                        }
+                    }
+                }
+            ]
+        )),
+        (status = 400, description = "The subject is not a governance"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governance_policies(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(governance_id): Path<String>,
+) -> Result<Json<Vec<Policy>>, Error> {
+    let governance = get_governance(&bridge, governance_id).await?;
+    Ok(Json(governance.policies))
+}
+
+/// Subject Export
+///
+/// Exports the complete history of a subject as a zip bundle that can be checked offline.
+/// The bundle holds the state of the subject, every event, the signatures of the last event and a manifest
+/// with the hash of each file and of the properties rebuilt from the events. The manifest is not signed, so
+/// it reveals a corrupted bundle but does not prove where it comes from.
+/// The bundle is streamed while the events are read from the node.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - The bundle as a zip file or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/export",
+    operation_id = "Subject Export",
+    tag = "Subject",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 200, description = "The bundle of the subject", content_type = "application/zip", body = Vec<u8>),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn export_subject(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(subject_id): Path<String>,
+) -> Result<Response, Error> {
+    let subject = match bridge.get_subject(subject_id.clone()).await {
+        Ok(response) => SubjectInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    let signatures = match bridge.get_signatures(subject_id.clone()).await {
+        Ok(response) => SignaturesInfo::from(response),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+
+    let governance_version = get_subject_governance(&bridge, &subject).await?.version;
+
+    let mut response = Response::new(export_bundle(
+        bridge,
+        subject,
+        signatures,
+        governance_version,
+    ));
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/zip"),
+    );
+    if let Ok(disposition) =
+        format!("attachment; filename=\"{}.zip\"", subject_id).parse::<HeaderValue>()
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    Ok(response)
+}
+
+/// Verify Bundle
+///
+/// Checks the consistency of a bundle exported from a subject and reports every check that
+/// failed and the sn it concerns. A report without errors does not prove that the bundle is
+/// authentic: signatures are not checked cryptographically and the manifest is not signed.
+///
+/// # Parameters
+///
+/// * `body: Bytes` - The bundle as a zip file.
+///
+/// # Returns
+///
+/// * `Json<BundleReport>` - The result of the verification in JSON format.
+#[utoipa::path(
+    post,
+    path = "/verify/bundle",
+    operation_id = "Verify Bundle",
+    tag = "Subject",
+    request_body(content = Vec<u8>, content_type = "application/zip", description = "The bundle to verify"),
+    responses(
+        (status = 200, description = "The result of the verification", body = BundleReport,
+        example = json!(
+            {
+                "subject_id": "JukqvNApVZMlEBI5DrZlZWEUgZs9vdEC6MEmmAQpwmns",
+                "sn": 3,
+                "errors": [
+                    {
+                        "sn": 2,
+                        "file": "events/2.json",
+                        "reason": "The file is missing"
+                    }
+                ]
+            }
+        )),
+    )
+)]
+async fn verify_bundle_request(body: Bytes) -> Json<BundleReport> {
+    Json(verify_bundle(&body))
+}
+
+/// Subject
+///
+/// Allows obtaining the state of a subject, now or after a given event.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<StateQuery>` - The sn, fields and query of the state.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - The state of the subject in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}",
+    operation_id = "Subject",
+    tag = "Subject",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("parameters" = StateQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The state of the subject. A SubjectInfo is returned without sn, a SubjectState when a sn is given.", body = StateResponse),
+        (status = 304, description = "The state has not changed since the tag given in If-None-Match"),
+        (status = 400, description = "Invalid sn, fields or query"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject(
+    bridge: Extension<Arc<Bridge>>,
+    cache: Extension<Arc<ResponseCache>>,
+    subject_id: Path<String>,
+    parameters: Query<StateQuery>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    get_state(bridge, cache, subject_id, parameters, headers).await
+}
+
+/// Subject Diff
+///
+/// Allows obtaining the changes of the state of a subject between two events.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<DiffQuery>` - The range of events to compare.
+///
+/// # Returns
+///
+/// * `Result<Json<StateDiff>, Error>` - The changes in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/diff",
+    operation_id = "Subject Diff",
+    tag = "State",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("parameters" = DiffQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The changes of the state between both events", body = StateDiff),
+        (status = 400, description = "Invalid range of events, or too many events to replay"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_diff(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+    parameters: Query<DiffQuery>,
+) -> Result<Json<StateDiff>, Error> {
+    get_state_diff(bridge, subject_id, parameters).await
+}
+
+/// Subject Event List
+///
+/// Allows obtaining the events of a subject using cursors keyed by sn.
+/// The cursors to the adjacent pages are also returned in a `Link` header.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<EventsCursorQuery>` - The pagination and filter parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<(HeaderMap, Json<CursorEvents>), Error>` - A page of events in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/events",
+    operation_id = "Subject Event List",
+    tag = "Event",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("parameters" = EventsCursorQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "A page of events of the subject", body = CursorEvents,
+        headers(
+            ("Link" = String, description = "Links to the next and previous pages")
+        )),
+        (status = 400, description = "Invalid cursor"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_events(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(subject_id): Path<String>,
+    Query(parameters): Query<EventsCursorQuery>,
+) -> Result<(HeaderMap, Json<CursorEvents>), Error> {
+    events_cursor(
+        &bridge,
+        &format!("{}/subjects/{}/events", API_PREFIX, subject_id),
+        &subject_id,
+        parameters,
+    )
+    .await
+}
+
+/// Subject Event
+///
+/// Allows obtaining the event of a subject with a given sn.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Extension(cache): Extension<Arc<ResponseCache>>` - The response cache wrapped in an `Arc`.
+/// * `Path((subject_id, sn)): Path<(String, u64)>` - The identifier of the subject and the sn of the event.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - The event in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/events/{sn}",
+    operation_id = "Subject Event",
+    tag = "Event",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("sn" = u64, Path, description = "Sequence number of the event"),
+    ),
+    responses(
+        (status = 200, description = "The event of the subject", body = EventInfo),
+        (status = 304, description = "The tag given in If-None-Match is the one of the event"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_event(
+    bridge: Extension<Arc<Bridge>>,
+    cache: Extension<Arc<ResponseCache>>,
+    Path((subject_id, sn)): Path<(String, u64)>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    get_event_sn(
+        bridge,
+        cache,
+        Path(subject_id),
+        Query(EventSnQuery { sn }),
+        headers,
+    )
+    .await
+}
+
+/// Subject Signatures
+///
+/// Allows obtaining the signatures of the last event of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - The signatures in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/signatures",
+    operation_id = "Subject Signatures",
+    tag = "Signature",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 200, description = "The signatures of the last event of the subject", body = SignaturesInfo),
+        (status = 304, description = "The signatures have not changed since the tag given in If-None-Match"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_signatures(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    get_signatures(bridge, subject_id, headers).await
+}
+
+/// Subject Signatures Quorum
+///
+/// Allows checking the signatures of an event of a subject against the quorums of its governance.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<SignaturesQuery>` - The sn of the event.
+///
+/// # Returns
+///
+/// * `Result<Json<SignaturesQuorum>, Error>` - The quorum of each protocol in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/signatures/quorum",
+    operation_id = "Subject Signatures Quorum",
+    tag = "Signature",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("parameters" = SignaturesQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The quorum of each protocol", body = SignaturesQuorum),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_signatures_quorum(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+    parameters: Query<SignaturesQuery>,
+) -> Result<Json<SignaturesQuorum>, Error> {
+    get_signatures_quorum(bridge, subject_id, parameters).await
+}
+
+/// Subject Witnesses
+///
+/// Allows obtaining the witnesses authorized to send copies of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<String>>, Error>` - A list of witness nodes in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/witnesses",
+    operation_id = "Subject Witnesses",
+    tag = "Auth",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 200, description = "A list of witness nodes", body = [String]),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_witnesses(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<Json<Vec<String>>, Error> {
+    get_witnesses_subject(bridge, subject_id).await
+}
+
+/// Authorize Subject Witnesses
+///
+/// Authorizes a subject, replacing the witnesses that will send copies of it.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Json(witnesses): Json<Vec<String>>` - The witnesses in JSON format.
+///
+/// # Returns
+///
+/// * `Result<StatusCode, Error>` - No content or an error if the request fails.
+#[utoipa::path(
+    put,
+    path = "/subjects/{subject_id}/witnesses",
+    operation_id = "Authorize Subject Witnesses",
+    tag = "Auth",
+    request_body(content = [String], content_type = "application/json", description = "witnesses"),
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 204, description = "The subject has been authorized"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn put_subject_witnesses(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+    witnesses: Json<Vec<String>>,
+) -> Result<StatusCode, Error> {
+    put_auth(bridge, subject_id, witnesses).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove Subject Witnesses
+///
+/// Removes the authorization of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<StatusCode, Error>` - No content or an error if the request fails.
+#[utoipa::path(
+    delete,
+    path = "/subjects/{subject_id}/witnesses",
+    operation_id = "Remove Subject Witnesses",
+    tag = "Auth",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 204, description = "The authorization has been removed"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn delete_subject_witnesses(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<StatusCode, Error> {
+    delete_auth_subject(bridge, subject_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Subject Approval
+///
+/// Allows obtaining the pending approval request of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<ApproveInfo>, Error>` - The approval request in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/subjects/{subject_id}/approval",
+    operation_id = "Subject Approval",
+    tag = "Approval",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 200, description = "The approval request of the subject", body = ApproveInfo),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_subject_approval(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<Json<ApproveInfo>, Error> {
+    get_approval(bridge, subject_id).await
+}
+
+/// Vote Subject Approval
+///
+/// Accepts or rejects the pending approval request of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Json(response): Json<String>` - The response (approval or rejection) in JSON format.
+///
+/// # Returns
+///
+/// * `Result<Json<String>, Error>` - The new state of the request in JSON format or an error if the request fails.
+#[utoipa::path(
+    patch,
+    path = "/subjects/{subject_id}/approval",
+    operation_id = "Vote Subject Approval",
+    tag = "Approval",
+    request_body(content = String, content_type = "application/json", description = "Vote of the user for an existing request"),
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 200, description = "Request successfully voted", body = String),
+        (status = 400, description = "The subject is not active, or the governance does not define its schema"),
+        (status = 412, description = "The subject has changed since the version given in If-Match"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn patch_subject_approval(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+    headers: HeaderMap,
+    response: Json<String>,
+) -> Result<Json<String>, Error> {
+    patch_approval(bridge, subject_id, headers, response).await
+}
+
+/// Request Subject Update
+///
+/// Asks the witnesses of a subject for its latest events. The update runs in the background.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<(StatusCode, Json<String>), Error>` - A message in JSON format or an error if the request fails.
+#[utoipa::path(
+    post,
+    path = "/subjects/{subject_id}/updates",
+    operation_id = "Request Subject Update",
+    tag = "Update",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 202, description = "The update has been started", body = String,
+        example = json!(
+            "Update in progress"
+        )),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn post_subject_update(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<(StatusCode, Json<String>), Error> {
+    Ok((
+        StatusCode::ACCEPTED,
+        update_subject(bridge, subject_id).await?,
+    ))
+}
+
+/// Request Subject Distribution
+///
+/// Sends the last event of a subject to its witnesses. The distribution runs in the background.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<(StatusCode, Json<String>), Error>` - A message in JSON format or an error if the request fails.
+#[utoipa::path(
+    post,
+    path = "/subjects/{subject_id}/distributions",
+    operation_id = "Request Subject Distribution",
+    tag = "Update",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 202, description = "The distribution has been started", body = String,
+        example = json!(
+            "Manual update in progress"
+        )),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn post_subject_distribution(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<(StatusCode, Json<String>), Error> {
+    Ok((
+        StatusCode::ACCEPTED,
+        manual_distribution(bridge, subject_id).await?,
+    ))
+}
+
+/// Request Subject Transfer Check
+///
+/// Checks the pending transfer of a subject.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<(StatusCode, Json<String>), Error>` - A message in JSON format or an error if the request fails.
+#[utoipa::path(
+    post,
+    path = "/subjects/{subject_id}/transfer-checks",
+    operation_id = "Request Subject Transfer Check",
+    tag = "Transfer",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+    ),
+    responses(
+        (status = 202, description = "The check has been started", body = String),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn post_subject_transfer_check(
+    bridge: Extension<Arc<Bridge>>,
+    subject_id: Path<String>,
+) -> Result<(StatusCode, Json<String>), Error> {
+    Ok((
+        StatusCode::ACCEPTED,
+        check_transfer(bridge, subject_id).await?,
+    ))
+}
+
+/// Governance List
+///
+/// Gets the governances to which the node belongs. The total number of matching governances is
+/// returned in the `X-Total-Count` header.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Query(parameters): Query<GovQuery>` - The query parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<(HeaderMap, Json<Vec<GovsData>>), Error>` - A JSON with governance information or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances",
+    operation_id = "Governance List",
+    tag = "Governance",
+    params(
+        ("parameters" = GovQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The governances to which the node belongs", body = [GovsData],
+        headers(
+            ("X-Total-Count" = u64, description = "Number of governances matching the filters")
+        )),
+        (status = 400, description = "Governances can not be sorted by schema, or too many governances to sort by sn"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governances(
+    bridge: Extension<Arc<Bridge>>,
+    cache: Extension<Arc<ResponseCache>>,
+    parameters: Query<GovQuery>,
+) -> Result<(HeaderMap, Json<Vec<GovsData>>), Error> {
+    get_all_govs(bridge, cache, parameters).await
+}
+
+/// Governance Subjects
+///
+/// Gets the subjects of a governance. The total number of matching subjects is returned in the
+/// `X-Total-Count` header.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+/// * `Query(parameters): Query<SubjectQuery>` - The query parameters for the request.
+///
+/// # Returns
+///
+/// * `Result<(HeaderMap, Json<Vec<RegisterDataSubj>>), Error>` - A JSON with subject information or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/subjects",
+    operation_id = "Governance Subjects",
+    tag = "Subject",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+        ("parameters" = SubjectQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The subjects of the governance", body = [RegisterDataSubj],
+        headers(
+            ("X-Total-Count" = u64, description = "Number of subjects matching the filters")
+        )),
+        (status = 400, description = "Too many subjects to filter by namespace or sort by sn"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_governance_subjects(
+    bridge: Extension<Arc<Bridge>>,
+    cache: Extension<Arc<ResponseCache>>,
+    governance_id: Path<String>,
+    parameters: Query<SubjectQuery>,
+) -> Result<(HeaderMap, Json<Vec<RegisterDataSubj>>), Error> {
+    get_all_subjects(bridge, cache, governance_id, parameters).await
+}
+
+/// Create Request
+///
+/// Sends a signed event request. The request is processed in the background; its state can be
+/// followed at the url of the `Location` header.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Extension(cache): Extension<Arc<ResponseCache>>` - The response cache wrapped in an `Arc`.
+/// * `Json(request): Json<BridgeSignedEventRequest>` - The signed event request in JSON format.
+///
+/// # Returns
+///
+/// * `Result<(StatusCode, HeaderMap, Json<RequestData>), Error>` - The identifiers of the request and the subject in JSON format or an error if the request fails.
+#[utoipa::path(
+    post,
+    path = "/requests",
+    operation_id = "Create Request",
+    tag = "Request",
+    request_body(content = String, content_type = "application/json", description = "The signed event request"),
+    responses(
+        (status = 201, description = "Request Created Successfully", body = RequestData,
+        headers(
+            ("Location" = String, description = "Url of the state of the request")
+        )),
+        (status = 400, description = "The subject is not active, or the governance does not define its schema"),
+        (status = 412, description = "The subject has changed since the version given in If-Match"),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn post_request(
+    bridge: Extension<Arc<Bridge>>,
+    cache: Extension<Arc<ResponseCache>>,
+    headers: HeaderMap,
+    request: Json<BridgeSignedEventRequest>,
+) -> Result<(StatusCode, HeaderMap, Json<RequestData>), Error> {
+    let Json(data) = send_event_request(bridge, cache, headers, request).await?;
+
+    let mut headers = HeaderMap::new();
+    if let Ok(location) =
+        HeaderValue::from_str(&format!("{}/requests/{}", API_PREFIX, data.request_id))
+    {
+        headers.insert(header::LOCATION, location);
+    }
+
+    Ok((StatusCode::CREATED, headers, Json(data)))
+}
+
+/// Request
+///
+/// Allows obtaining the state of an event request.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(request_id): Path<String>` - The identifier of the event request as a path parameter.
+///
+/// # Returns
+///
+/// * `Result<Json<RequestInfo>, Error>` - The state of the request in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/requests/{request_id}",
+    operation_id = "Request",
+    tag = "Request",
+    params(
+        ("request_id" = String, Path, description = "Event Request's unique id"),
+    ),
+    responses(
+        (status = 200, description = "The state of the request", body = RequestInfo),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_request(
+    bridge: Extension<Arc<Bridge>>,
+    request_id: Path<String>,
+) -> Result<Json<RequestInfo>, Error> {
+    get_request_state(bridge, request_id).await
+}
+
+/// Transfer List
+///
+/// Gets the transfers waiting to be confirmed or rejected.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+///
+/// # Returns
+///
+/// * `Result<Json<Vec<TransferSubject>>, Error>` - The pending transfers in JSON format or an error if the request fails.
+#[utoipa::path(
+    get,
+    path = "/transfers",
+    operation_id = "Transfer List",
+    tag = "Transfer",
+    responses(
+        (status = 200, description = "The pending transfers", body = [TransferSubject]),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn get_transfers(
+    bridge: Extension<Arc<Bridge>>,
+) -> Result<Json<Vec<TransferSubject>>, Error> {
+    get_pending_transfers(bridge).await
+}
+
+/// Export Subject Events
+///
+/// Streams every event of a subject as newline-delimited JSON or as CSV, with the request of
+/// each event flattened into columns. The events are sent as they are read from the node.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(subject_id): Path<String>` - The identifier of the subject as a path parameter.
+/// * `Query(parameters): Query<ExportQuery>` - The format of the export, ndjson by default.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - A chunked response with the events or an error if the subject can not be read.
+#[utoipa::path(
+    get,
+    path = "/events/{subject_id}/export",
+    operation_id = "Export Subject Events",
+    tag = "Event",
+    params(
+        ("subject_id" = String, Path, description = "Subject unique id"),
+        ("parameters" = ExportQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The events of the subject, one per line", content(
+            (String = "application/x-ndjson"),
+            (String = "text/csv")
+        )),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn export_subject_events(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(subject_id): Path<String>,
+    Query(parameters): Query<ExportQuery>,
+) -> Result<Response, Error> {
+    if let Err(e) = bridge.get_subject(subject_id.clone()).await {
+        return Err(Error::Kore(e.to_string()));
+    }
+
+    let format = parameters.format.unwrap_or_default();
+    Ok(export_response(
+        export_events(bridge, vec![subject_id.clone()], format),
+        format,
+        &subject_id,
+    ))
+}
+
+/// Export Governance Events
+///
+/// Streams every event of a governance and of each of its subjects, one subject after another,
+/// as newline-delimited JSON or as CSV.
+///
+/// # Parameters
+///
+/// * `Extension(bridge): Extension<Arc<Bridge>>` - The bridge extension wrapped in an `Arc`.
+/// * `Path(governance_id): Path<String>` - The identifier of the governance as a path parameter.
+/// * `Query(parameters): Query<ExportQuery>` - The format of the export, ndjson by default.
+///
+/// # Returns
+///
+/// * `Result<Response, Error>` - A chunked response with the events or an error if the governance can not be read.
+#[utoipa::path(
+    get,
+    path = "/governances/{governance_id}/events/export",
+    operation_id = "Export Governance Events",
+    tag = "Event",
+    params(
+        ("governance_id" = String, Path, description = "Governance unique id"),
+        ("parameters" = ExportQuery, Query, description = "The query parameters for the request"),
+    ),
+    responses(
+        (status = 200, description = "The events of the governance and its subjects, one per line", content(
+            (String = "application/x-ndjson"),
+            (String = "text/csv")
+        )),
+        (status = 500, description = "Internal Server Error"),
+    )
+)]
+async fn export_governance_events(
+    Extension(bridge): Extension<Arc<Bridge>>,
+    Path(governance_id): Path<String>,
+    Query(parameters): Query<ExportQuery>,
+) -> Result<Response, Error> {
+    let subjects = match bridge
+        .get_all_subjs(governance_id.clone(), None, None)
+        .await
+    {
+        Ok(response) => response.into_iter().map(|x| x.subject_id),
+        Err(e) => return Err(Error::Kore(e.to_string())),
+    };
+    let subject_ids = std::iter::once(governance_id.clone())
+        .chain(subjects)
+        .collect();
+
+    let format = parameters.format.unwrap_or_default();
+    Ok(export_response(
+        export_events(bridge, subject_ids, format),
+        format,
+        &governance_id,
+    ))
+}
+
+fn export_response(body: Body, format: ExportFormat, name: &str) -> Response {
+    let mut response = Response::new(body);
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(content_type(format)),
+    );
+
+    let extension = match format {
+        ExportFormat::Ndjson => "ndjson",
+        ExportFormat::Csv => "csv",
+    };
+    if let Ok(disposition) =
+        format!("attachment; filename=\"{}.{}\"", name, extension).parse::<HeaderValue>()
+    {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    response
+}
+
+/// Readiness
+///
+/// Tells whether the node takes new requests, so that load balancers stop sending them once it
+/// starts shutting down.
+///
+/// # Parameters
+///
+/// * `Extension(shutdown): Extension<Shutdown>` - The shutdown of the server.
+///
+/// # Returns
+///
+/// * `(StatusCode, Json<String>)` - 200 if the node is ready or 503 if it is shutting down.
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    operation_id = "Readiness",
+    tag = "Other",
+    responses(
+        (status = 200, description = "The node takes new requests", body = String,
+        example = json!("ready")),
+        (status = 503, description = "The node is shutting down", body = String,
+        example = json!("shutting down")),
+    )
+)]
+async fn get_readiness(Extension(shutdown): Extension<Shutdown>) -> (StatusCode, Json<String>) {
+    if shutdown.is_ready() {
+        (StatusCode::OK, Json("ready".to_owned()))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json("shutting down".to_owned()),
+        )
+    }
+}
+
+/// Cache Metrics
+///
+/// Gets the hits and misses of each cached route and the memory used by the response cache.
+///
+/// # Parameters
+///
+/// * `Extension(cache): Extension<Arc<ResponseCache>>` - The response cache wrapped in an `Arc`.
+///
+/// # Returns
+///
+/// * `Json<CacheMetrics>` - The metrics of the cache in JSON format.
+#[utoipa::path(
+    get,
+    path = "/cache-metrics",
+    operation_id = "Cache Metrics",
+    tag = "Other",
+    responses(
+        (status = 200, description = "The metrics of the response cache", body = CacheMetrics,
+        example = json!(
+            {
+                "enabled": true,
+                "entries": 2,
+                "memory": 1830,
+                "max_memory": 33554432,
+                "routes": [
+                    {
+                        "route": "governances",
+                        "ttl": 5000,
+                        "hits": 120,
+                        "misses": 3
+                    },
+                    {
+                        "route": "subjects",
+                        "ttl": 5000,
+                        "hits": 0,
+                        "misses": 0
+                    },
+                    {
+                        "route": "state",
+                        "ttl": 5000,
+                        "hits": 87,
+                        "misses": 5
+                    }
+                ]
+            }
+        )),
+    )
+)]
+async fn get_cache_metrics(Extension(cache): Extension<Arc<ResponseCache>>) -> Json<CacheMetrics> {
+    Json(cache.metrics())
+}
+
+/// Prefix of the current version of the API. The same routes are still served at the root,
+/// as deprecated aliases.
+pub const API_PREFIX: &str = "/v1";
+
+pub fn build_routes(
+    bridge: Arc<Bridge>,
+    cache: Arc<ResponseCache>,
+    streams: StreamLimit,
+    shutdown: Shutdown,
+) -> Router {
+    let requests_limit = DefaultBodyLimit::max(build_body_limit("REQUESTS"));
+    let bundles_limit = DefaultBodyLimit::max(build_body_limit("BUNDLES"));
+    let streams_limit = from_fn_with_state(streams.clone(), limit_stream);
+    let routes = Router::new()
+        .route("/signatures/{subject_id}", get(get_signatures))
+        .route(
+            "/signatures/{subject_id}/quorum",
+            get(get_signatures_quorum),
+        )
+        .route("/state/{subject_id}", get(get_state))
+        .route("/state/{subject_id}/diff", get(get_state_diff))
+        .route("/events/{subject_id}", get(get_events))
+        .route("/event/{subject_id}", get(get_event_sn))
+        .route(
+            "/events-first-last/{subject_id}",
+            get(get_first_or_end_events),
+        )
+        .route("/register-subjects/{governance_id}", get(get_all_subjects))
+        .route("/register-governances", get(get_all_govs))
+        .route(
+            "/governances/{governance_id}/members",
+            get(get_governance_members),
+        )
+        .route("/governances/{governance_id}/roles", get(get_governance_roles))
+        .route(
+            "/governances/{governance_id}/schemas",
+            get(get_governance_schemas),
+        )
+        .route(
+            "/governances/{governance_id}/policies",
+            get(get_governance_policies),
+        )
+        .route("/update/{subject_id}", post(update_subject))
+        .route("/check-transfer/{subject_id}", post(check_transfer))
+        .route(
+            "/manual-distribution/{subject_id}",
+            post(manual_distribution),
+        )
+        .route("/auth/{subject_id}", delete(delete_auth_subject))
+        .route("/auth/{subject_id}", get(get_witnesses_subject))
+        .route("/auth", get(get_all_auth_subjects))
+        .route("/auth/{subject_id}", put(put_auth))
+        .route("/approval-request/{subject_id}", patch(patch_approval))
+        .route("/approval-request/{subject_id}", get(get_approval))
+        .route("/event-request/{request_id}", get(get_request_state))
+        .route(
+            "/event-request",
+            post(send_event_request).layer(requests_limit),
+        )
+        .route(
+            "/subjects/{subject_id}/export",
+            get(export_subject).layer(streams_limit.clone()),
+        )
+        .route(
+            "/verify/bundle",
+            post(verify_bundle_request).layer(bundles_limit),
+        )
+        .route("/controller-id", get(get_controller_id))
+        .route("/peer-id", get(get_peer_id))
+        .route("/config", get(get_config))
+        .route("/keys", get(get_keys))
+        .route("/pending-transfers", get(get_pending_transfers))
+        .layer(
+            ServiceBuilder::new()
+                .layer(Extension(bridge.clone()))
+                .layer(Extension(cache.clone())),
+        );
+
+    let resources = Router::new()
+        .route("/subjects/{subject_id}", get(get_subject))
+        .route("/subjects/{subject_id}/diff", get(get_subject_diff))
+        .route("/subjects/{subject_id}/events", get(get_subject_events))
+        .route("/subjects/{subject_id}/events/{sn}", get(get_subject_event))
+        .route(
+            "/subjects/{subject_id}/signatures",
+            get(get_subject_signatures),
+        )
+        .route(
+            "/subjects/{subject_id}/signatures/quorum",
+            get(get_subject_signatures_quorum),
+        )
+        .route(
+            "/subjects/{subject_id}/witnesses",
+            get(get_subject_witnesses)
+                .put(put_subject_witnesses)
+                .delete(delete_subject_witnesses),
+        )
+        .route(
+            "/subjects/{subject_id}/approval",
+            get(get_subject_approval).patch(patch_subject_approval),
+        )
+        .route("/subjects/{subject_id}/updates", post(post_subject_update))
+        .route(
+            "/subjects/{subject_id}/distributions",
+            post(post_subject_distribution),
+        )
+        .route(
+            "/subjects/{subject_id}/transfer-checks",
+            post(post_subject_transfer_check),
+        )
+        .route("/governances", get(get_governances))
+        .route(
+            "/governances/{governance_id}/subjects",
+            get(get_governance_subjects),
+        )
+        .route("/requests", post(post_request).layer(requests_limit))
+        .route("/requests/{request_id}", get(get_request))
+        .route("/transfers", get(get_transfers))
+        .route("/cache-metrics", get(get_cache_metrics))
+        .route("/health/ready", get(get_readiness))
+        .route(
+            "/events/{subject_id}/export",
+            get(export_subject_events).layer(streams_limit.clone()),
+        )
+        .route(
+            "/governances/{governance_id}/events/export",
+            get(export_governance_events).layer(streams_limit.clone()),
+        )
+        .layer(
+            ServiceBuilder::new()
+                .layer(Extension(bridge.clone()))
+                .layer(Extension(cache.clone()))
+                .layer(Extension(shutdown.clone())),
+        );
+
+    // The resource routes only exist under the version prefix, the rest are also served at the
+    // root as deprecated aliases.
+    let api = routes.clone().merge(resources);
+    // The socket forwards its calls to the complete service, set once it is built below.
+    let service = Arc::new(OnceLock::new());
+    let ws = crate::ws::build_ws_routes(bridge.clone(), service.clone(), shutdown.clone())
+        .layer(streams_limit.clone());
+
+    let routes = Router::new()
+        .nest(API_PREFIX, api.merge(ws.clone()))
+        .merge(deprecated_alias(
+            routes.merge(ws),
+            API_PREFIX,
+            build_deprecated_since(),
+            build_sunset(),
+        ));
+    let routes = negotiate_format(routes);
+
+    #[cfg(feature = "graphql")]
+    let routes = routes.merge(crate::graphql::build_graphql_routes(
+        bridge.clone(),
+        streams.clone(),
+        shutdown.clone(),
+    ));
+
+    // With an address of its own the gRPC service is served apart, see `grpc::serve_grpc`.
+    #[cfg(feature = "grpc")]
+    let routes = if crate::enviroment::build_address_grpc().is_empty() {
+        routes.merge(crate::grpc::build_grpc_routes(
+            bridge, cache, streams, shutdown,
+        ))
+    } else {
+        routes
+    };
+
+    let routes = if build_doc() {
+        Router::new()
+            .merge(routes)
+            .merge(RapiDoc::with_openapi("/doc/koreapi.json", ApiDoc::openapi()).path("/doc"))
+    } else {
+        Router::new().merge(routes)
+    };
+
+    let routes = compression(routes, build_compression_min_size());
+    let routes = body_limits(routes, build_body_limit("DEFAULT"), build_body_timeout());
+    let routes = load_shedding(routes, build_request_timeout(), build_max_concurrency());
+    let _ = service.set(routes.clone());
+    routes
 }
@@ -0,0 +1,110 @@
+use std::{future::IntoFuture, io, sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Shutdown of the server, shared by the listeners and the readiness probe.
+///
+/// Once started the node reports that it is not ready and the open subscriptions end. After a
+/// delay, which gives load balancers time to see that the node is not ready, the listeners
+/// stop accepting connections and the requests in progress are given a deadline to finish.
+#[derive(Clone)]
+pub struct Shutdown {
+    started: Arc<watch::Sender<bool>>,
+    delay: Duration,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl Shutdown {
+    /// A shutdown whose listeners close `delay` after it starts.
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            started: Arc::new(watch::Sender::new(false)),
+            delay,
+        }
+    }
+
+    /// Whether the node takes new requests, false once the shutdown has started.
+    pub fn is_ready(&self) -> bool {
+        !*self.started.borrow()
+    }
+
+    /// Starts the shutdown, waking every task waiting on [`Shutdown::started`].
+    pub fn start(&self) {
+        self.started.send_replace(true);
+    }
+
+    /// Waits until the shutdown starts.
+    pub async fn started(&self) {
+        let mut started = self.started.subscribe();
+        let _ = started.wait_for(|started| *started).await;
+    }
+
+    /// Waits until the listeners must stop accepting connections, the delay after the shutdown
+    /// starts.
+    pub async fn closing(&self) {
+        self.started().await;
+        tokio::time::sleep(self.delay).await;
+    }
+
+    /// Runs `server`, whose graceful shutdown must wait on [`Shutdown::closing`], until it has
+    /// drained its connections or `timeout` has passed since the listeners started closing, in
+    /// which case the requests still in progress are dropped.
+    pub async fn drain<S>(&self, server: S, timeout: Duration) -> io::Result<()>
+    where
+        S: IntoFuture<Output = io::Result<()>>,
+    {
+        let server = server.into_future();
+        tokio::pin!(server);
+
+        tokio::select! {
+            result = &mut server => return result,
+            _ = self.closing() => {}
+        }
+
+        match tokio::time::timeout(timeout, server).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("Connections still open after {:?}, closing them", timeout);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Waits for SIGINT or, on Unix, SIGTERM.
+pub async fn termination_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Error listening for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Error listening for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("SIGINT received, shutting down"),
+        _ = terminate => info!("SIGTERM received, shutting down"),
+    }
+}
@@ -0,0 +1,453 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use json_patch::Patch;
+use serde_json::{Map, Value, json};
+use serde_json_path::JsonPath;
+
+use crate::wrappers::{EventInfo, EventRequestInfo, FieldChange};
+
+/// Applies a JSON Patch, as stored in the events of a subject, to a state.
+pub fn apply_patch(state: &mut Value, patch: &Value) -> Result<(), String> {
+    let patch: Patch =
+        serde_json::from_value(patch.clone()).map_err(|e| format!("Invalid JSON Patch: {}", e))?;
+
+    json_patch::patch(state, &patch).map_err(|e| format!("Can not apply patch: {}", e))
+}
+
+/// Maximum number of events replayed to rebuild a past state of a subject.
+pub const MAX_REPLAY: u64 = 10_000;
+
+/// Rebuilds the state of a subject by replaying its events from genesis, one at a time.
+///
+/// The patch of the genesis event holds the initial state, and the patches of the
+/// successful facts are applied over it in order. On a governance, a confirmed transfer also
+/// makes the new owner its `Owner` member, keeping the old owner as a member under the name
+/// given in the confirmation, if any.
+pub struct Replay {
+    governance: bool,
+    state: Option<Value>,
+    next_sn: u64,
+    new_owner: Option<String>,
+}
+
+impl Replay {
+    pub fn new(governance: bool) -> Self {
+        Self {
+            governance,
+            state: None,
+            next_sn: 0,
+            new_owner: None,
+        }
+    }
+
+    /// Applies the next event, which must follow the last one applied.
+    pub fn apply(&mut self, event: &EventInfo) -> Result<(), String> {
+        if event.sn != self.next_sn {
+            return Err(match self.state {
+                None => format!("Expected genesis event, found sn {}", event.sn),
+                Some(_) => format!("Expected sn {}, found sn {}", self.next_sn, event.sn),
+            });
+        }
+        self.next_sn = event
+            .sn
+            .checked_add(1)
+            .ok_or("Sequence number out of range".to_owned())?;
+
+        let Some(state) = &mut self.state else {
+            self.state = Some(match &event.patch {
+                Some(Value::String(raw)) => serde_json::from_str(raw)
+                    .map_err(|e| format!("Invalid genesis state: {}", e))?,
+                Some(patch) => patch.clone(),
+                None => return Err("The genesis event has no initial state".to_owned()),
+            });
+            return Ok(());
+        };
+        if !event.succes {
+            return Ok(());
+        }
+
+        let applied = match &event.event_req {
+            EventRequestInfo::Fact(..) => match &event.patch {
+                Some(patch) => apply_patch(state, patch),
+                None => Ok(()),
+            },
+            EventRequestInfo::Transfer(transfer) => {
+                self.new_owner = Some(transfer.new_owner.clone());
+                Ok(())
+            }
+            EventRequestInfo::Reject(..) => {
+                self.new_owner = None;
+                Ok(())
+            }
+            EventRequestInfo::Confirm(confirm) => match self.new_owner.take() {
+                Some(new_owner) if self.governance => {
+                    change_owner(state, &new_owner, confirm.name_old_owner.as_deref())
+                }
+                Some(_) => Ok(()),
+                None => Err("Confirmation without a pending transfer".to_owned()),
+            },
+            EventRequestInfo::Create(..) | EventRequestInfo::EOL(..) => Ok(()),
+        };
+        applied.map_err(|e| format!("sn {}: {}", event.sn, e))
+    }
+
+    /// The state rebuilt from the events applied so far, once the genesis event is applied.
+    pub fn state(&self) -> Option<&Value> {
+        self.state.as_ref()
+    }
+}
+
+/// Rebuilds the state of a subject by replaying its events from genesis, as [`Replay`] does.
+/// Events must start at sn 0 and be sorted.
+pub fn replay(events: &[EventInfo], governance: bool) -> Result<Value, String> {
+    let mut replay = Replay::new(governance);
+    for event in events {
+        replay.apply(event)?;
+    }
+
+    replay
+        .state
+        .ok_or("There are no events to replay".to_owned())
+}
+
+/// Makes `new_owner` the `Owner` member of a governance, as a confirmed transfer does, keeping
+/// the old owner as a member named `name_old_owner`, if given.
+fn change_owner(
+    state: &mut Value,
+    new_owner: &str,
+    name_old_owner: Option<&str>,
+) -> Result<(), String> {
+    let Some(members) = state.get_mut("members").and_then(Value::as_array_mut) else {
+        return Err("The governance has no members".to_owned());
+    };
+
+    let is_owner = |member: &Value| member.get("name").and_then(Value::as_str) == Some("Owner");
+    let Some(owner) = members.iter_mut().find(|x| is_owner(x)) else {
+        return Err("The governance has no Owner member".to_owned());
+    };
+    let old_owner = owner.get("id").cloned().unwrap_or_default();
+    owner["id"] = Value::String(new_owner.to_owned());
+
+    members.retain(|x| is_owner(x) || x.get("id").and_then(Value::as_str) != Some(new_owner));
+    if let Some(name) = name_old_owner {
+        members.push(json!({ "id": old_owner, "name": name }));
+    }
+
+    Ok(())
+}
+
+/// Computes the Blake3 hash of the canonical JSON serialization of the properties of a subject.
+///
+/// Object keys are serialized in lexicographic order, so the same properties always yield the
+/// same hash. It can not be compared with the state hashes of the node, which hash its binary
+/// encoding of the whole subject.
+pub fn properties_hash(state: &Value) -> String {
+    let bytes = serde_json::to_vec(&canonical(state)).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(blake3::hash(&bytes).as_bytes())
+}
+
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(
+                keys.into_iter()
+                    .map(|key| (key.clone(), canonical(&map[key])))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Computes the changes between the states of a subject at two sequence numbers.
+///
+/// Events must start at sn 0 and reach at least `to`. Returns the combined JSON Patch and the
+/// change of each field, along with the events whose patches touched it.
+pub fn diff(
+    events: &[EventInfo],
+    from: u64,
+    to: u64,
+    governance: bool,
+) -> Result<(Value, Vec<FieldChange>), String> {
+    let (Ok(from_index), Ok(to_index)) = (usize::try_from(from), usize::try_from(to)) else {
+        return Err("Sequence number out of range".to_owned());
+    };
+    if from > to || to_index >= events.len() {
+        return Err(format!(
+            "Invalid range of sequence numbers {}..{}",
+            from, to
+        ));
+    }
+
+    let initial = replay(&events[..=from_index], governance)?;
+    let last = replay(&events[..=to_index], governance)?;
+    let patch = json_patch::diff(&initial, &last);
+
+    let touched: Vec<(u64, Vec<String>)> = events[from_index + 1..=to_index]
+        .iter()
+        .filter(|x| x.succes)
+        .filter_map(|x| {
+            if governance && matches!(x.event_req, EventRequestInfo::Confirm(..)) {
+                return Some((x.sn, vec!["/members".to_owned()]));
+            }
+            if !matches!(x.event_req, EventRequestInfo::Fact(..)) {
+                return None;
+            }
+
+            let ops = x.patch.as_ref()?.as_array()?;
+            let paths = ops
+                .iter()
+                .flat_map(|op| [op.get("path"), op.get("from")])
+                .flatten()
+                .filter_map(|path| path.as_str().map(str::to_owned))
+                .collect();
+            Some((x.sn, paths))
+        })
+        .collect();
+
+    let changes = patch
+        .iter()
+        .map(|op| {
+            let path = op.path().to_string();
+            let events = touched
+                .iter()
+                .filter(|(_, paths)| paths.iter().any(|x| related(x, &path)))
+                .map(|(sn, _)| *sn)
+                .collect();
+
+            FieldChange {
+                from: initial.pointer(&path).cloned(),
+                to: last.pointer(&path).cloned(),
+                path,
+                events,
+            }
+        })
+        .collect();
+
+    let patch = serde_json::to_value(patch).map_err(|e| e.to_string())?;
+    Ok((patch, changes))
+}
+
+/// Checks whether one JSON pointer is equal to, or nested under, the other.
+fn related(a: &str, b: &str) -> bool {
+    let nested = |parent: &str, child: &str| {
+        child
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+    nested(a, b) || nested(b, a)
+}
+
+/// Selects the fields of a state given a comma-separated list of JSON pointers.
+///
+/// Returns an object that maps each pointer to its value. Pointers that do not
+/// exist in the state are left out.
+pub fn project(state: &Value, fields: &str) -> Result<Value, String> {
+    let mut projection = Map::new();
+    for field in fields.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+        if !field.starts_with('/') {
+            return Err(format!("Invalid JSON pointer: {}", field));
+        }
+
+        if let Some(value) = state.pointer(field) {
+            projection.insert(field.to_owned(), value.clone());
+        }
+    }
+
+    Ok(Value::Object(projection))
+}
+
+/// Runs a JSONPath query over a state and returns the matching values.
+pub fn query(state: &Value, query: &str) -> Result<Value, String> {
+    let path = JsonPath::parse(query).map_err(|e| format!("Invalid JSONPath query: {}", e))?;
+    Ok(Value::Array(
+        path.query(state).all().into_iter().cloned().collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, response::IntoResponse};
+
+    use super::*;
+    use crate::{
+        server::{StateQuery, select_properties},
+        wrappers::{CreateRequestInfo, FactRequestInfo, Namespace},
+    };
+
+    fn genesis(state: Value) -> EventInfo {
+        EventInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn: 0,
+            patch: Some(state),
+            error: None,
+            event_req: EventRequestInfo::Create(CreateRequestInfo {
+                governance_id: "Jgovernance".to_owned(),
+                schema_id: "example".to_owned(),
+                namespace: Namespace::from(""),
+                name: None,
+                description: None,
+            }),
+            succes: true,
+        }
+    }
+
+    fn fact(sn: u64, patch: Value, succes: bool) -> EventInfo {
+        EventInfo {
+            subject_id: "Jsubject".to_owned(),
+            sn,
+            patch: Some(patch),
+            error: None,
+            event_req: EventRequestInfo::Fact(FactRequestInfo {
+                subject_id: "Jsubject".to_owned(),
+                payload: json!({}),
+            }),
+            succes,
+        }
+    }
+
+    fn replace(path: &str, value: Value) -> Value {
+        json!([{ "op": "replace", "path": path, "value": value }])
+    }
+
+    #[test]
+    fn replays_successful_facts_in_order() {
+        let events = [
+            genesis(json!({ "a": 1, "b": 1 })),
+            fact(1, replace("/a", json!(2)), true),
+            fact(2, replace("/b", json!(5)), false),
+            fact(3, replace("/b", json!(3)), true),
+        ];
+
+        assert_eq!(replay(&events, false).unwrap(), json!({ "a": 2, "b": 3 }));
+        assert_eq!(
+            replay(&events[..2], false).unwrap(),
+            json!({ "a": 2, "b": 1 })
+        );
+    }
+
+    #[test]
+    fn replay_stops_at_a_failing_patch() {
+        let events = [
+            genesis(json!({ "a": 1 })),
+            fact(1, replace("/a", json!(2)), true),
+            fact(2, json!([{ "op": "remove", "path": "/missing" }]), true),
+            fact(3, replace("/a", json!(3)), true),
+        ];
+
+        let error = replay(&events, false).unwrap_err();
+        assert!(error.starts_with("sn 2: Can not apply patch"), "{}", error);
+
+        // The patch of a failed event was never applied by the node.
+        let mut events = events;
+        events[2].succes = false;
+        assert_eq!(replay(&events, false).unwrap(), json!({ "a": 3 }));
+    }
+
+    #[test]
+    fn replay_rejects_an_sn_past_the_next_event() {
+        let mut replay = Replay::new(false);
+        assert!(
+            replay
+                .apply(&fact(1, replace("/a", json!(2)), true))
+                .is_err()
+        );
+        assert!(replay.state().is_none());
+
+        replay.apply(&genesis(json!({ "a": 1 }))).unwrap();
+        let error = replay
+            .apply(&fact(2, replace("/a", json!(2)), true))
+            .unwrap_err();
+        assert_eq!(error, "Expected sn 1, found sn 2");
+        assert_eq!(replay.state(), Some(&json!({ "a": 1 })));
+    }
+
+    #[test]
+    fn hash_does_not_depend_on_key_order() {
+        assert_eq!(
+            properties_hash(&json!({ "a": 1, "b": { "c": 2, "d": 3 } })),
+            properties_hash(&json!({ "b": { "d": 3, "c": 2 }, "a": 1 }))
+        );
+        assert_ne!(
+            properties_hash(&json!({ "a": 1 })),
+            properties_hash(&json!({ "a": 2 }))
+        );
+    }
+
+    #[test]
+    fn diff_of_equal_states_is_empty() {
+        let events = [
+            genesis(json!({ "a": 1 })),
+            fact(1, replace("/a", json!(2)), true),
+            fact(2, replace("/a", json!(1)), true),
+        ];
+
+        for (from, to) in [(0, 2), (1, 1)] {
+            let (patch, changes) = diff(&events, from, to, false).unwrap();
+            assert_eq!(patch, json!([]));
+            assert!(changes.is_empty());
+        }
+    }
+
+    #[test]
+    fn diff_lists_the_events_that_touched_each_field() {
+        let events = [
+            genesis(json!({ "a": 1, "b": { "c": 1 } })),
+            fact(1, replace("/a", json!(2)), true),
+            fact(2, replace("/b/c", json!(2)), true),
+            fact(3, replace("/b", json!({ "c": 3 })), true),
+        ];
+
+        let (_, changes) = diff(&events, 1, 3, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "/b/c");
+        assert_eq!(changes[0].from, Some(json!(1)));
+        assert_eq!(changes[0].to, Some(json!(3)));
+        assert_eq!(changes[0].events, vec![2, 3]);
+    }
+
+    #[test]
+    fn diff_rejects_an_sn_past_the_last_event() {
+        let events = [
+            genesis(json!({ "a": 1 })),
+            fact(1, replace("/a", json!(2)), true),
+        ];
+
+        assert!(diff(&events, 0, 2, false).is_err());
+        assert!(diff(&events, 1, 0, false).is_err());
+    }
+
+    #[test]
+    fn selects_fields_and_queries() {
+        let state = json!({ "a": { "b": 1 }, "c": [1, 2] });
+
+        assert_eq!(
+            project(&state, "/a/b, /missing,/c").unwrap(),
+            json!({ "/a/b": 1, "/c": [1, 2] })
+        );
+        assert_eq!(query(&state, "$.c[*]").unwrap(), json!([1, 2]));
+        assert_eq!(query(&state, "$.missing").unwrap(), json!([]));
+    }
+
+    #[test]
+    fn invalid_fields_and_queries_are_bad_requests() {
+        let state = json!({ "a": 1 });
+        let parameters = [
+            (Some("a"), None),
+            (None, Some("$.a[")),
+            (Some("/a"), Some("$.a")),
+        ];
+
+        for (fields, query) in parameters {
+            let parameters = StateQuery {
+                sn: None,
+                fields: fields.map(str::to_owned),
+                query: query.map(str::to_owned),
+            };
+            let error = select_properties(state.clone(), &parameters).unwrap_err();
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
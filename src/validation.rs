@@ -0,0 +1,78 @@
+use kore_bridge::{
+    Bridge,
+    model::{BridgeEventRequest, BridgeSignedEventRequest},
+};
+
+use crate::{cache::ResponseCache, error::Error, wrappers::Governance};
+
+/// Checks that an event request can apply to its subject before it is sent to the node.
+///
+/// Facts must target an active subject and new subjects a governance, and the governance must
+/// still define the schema of the subject. The JSON Schema of the governance describes the
+/// state, which only the contract can compute from a fact, so payloads are left to evaluation.
+/// Other kinds of events are let through.
+pub async fn validate_event_request(
+    bridge: &Bridge,
+    cache: &ResponseCache,
+    request: &BridgeSignedEventRequest,
+) -> Result<(), Error> {
+    match &request.request {
+        BridgeEventRequest::Create(create) => {
+            if create.schema_id == "governance" {
+                return Ok(());
+            }
+
+            let governance = governance(bridge, cache, create.governance_id.clone()).await?;
+            defines_schema(&governance, &create.governance_id, &create.schema_id)
+        }
+        BridgeEventRequest::Fact(fact) => {
+            let subject = cache.subject(bridge, fact.subject_id.clone()).await?;
+            if !subject.active {
+                return Err(Error::BadRequest(format!(
+                    "Subject {} is not active",
+                    subject.subject_id
+                )));
+            }
+
+            if subject.schema_id == "governance" {
+                return Ok(());
+            }
+
+            let governance = governance(bridge, cache, subject.governance_id.clone()).await?;
+            defines_schema(&governance, &subject.governance_id, &subject.schema_id)
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn governance(
+    bridge: &Bridge,
+    cache: &ResponseCache,
+    governance_id: String,
+) -> Result<Governance, Error> {
+    let subject = cache.subject(bridge, governance_id.clone()).await?;
+    if subject.schema_id != "governance" {
+        return Err(Error::BadRequest(format!(
+            "Subject {} is not a governance",
+            governance_id
+        )));
+    }
+
+    Governance::try_from(subject.properties)
+        .map_err(|e| Error::Kore(format!("Can not parse governance: {}", e)))
+}
+
+fn defines_schema(
+    governance: &Governance,
+    governance_id: &str,
+    schema_id: &str,
+) -> Result<(), Error> {
+    if governance.schemas.iter().any(|x| x.id == schema_id) {
+        Ok(())
+    } else {
+        Err(Error::BadRequest(format!(
+            "Governance {} does not define the schema {}",
+            governance_id, schema_id
+        )))
+    }
+}
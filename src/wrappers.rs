@@ -43,6 +43,7 @@ impl From<PaginatorEventsBridge> for PaginatorEvents {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct EventInfo {
     pub subject_id: String,
     pub sn: u64,
@@ -85,6 +86,7 @@ impl From<PaginatorBridge> for Paginator {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct ProtocolsError {
     pub evaluation: Option<String>,
     pub validation: Option<String>,
@@ -101,6 +103,7 @@ impl From<ProtocolsErrorBridge> for ProtocolsError {
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Union))]
 pub enum EventRequestInfo {
     Create(CreateRequestInfo),
     Fact(FactRequestInfo),
@@ -136,6 +139,7 @@ impl From<EventRequestInfoBridge> for EventRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct CreateRequestInfo {
     pub governance_id: String,
     pub schema_id: String,
@@ -157,6 +161,7 @@ impl From<CreateRequestInfoBridge> for CreateRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct TransferRequestInfo {
     pub subject_id: String,
     pub new_owner: String,
@@ -172,6 +177,7 @@ impl From<TransferRequestInfoBridge> for TransferRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct ConfirmRequestInfo {
     pub subject_id: String,
     pub name_old_owner: Option<String>,
@@ -187,6 +193,7 @@ impl From<ConfirmRequestInfoBridge> for ConfirmRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct RejectRequestInfo {
     pub subject_id: String,
 }
@@ -200,6 +207,7 @@ impl From<RejectRequestInfoBridge> for RejectRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct EOLRequestInfo {
     pub subject_id: String,
 }
@@ -213,6 +221,7 @@ impl From<EOLRequestInfoBridge> for EOLRequestInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct FactRequestInfo {
     pub subject_id: String,
     pub payload: Value,
@@ -230,6 +239,27 @@ impl From<FactRequestInfoBridge> for FactRequestInfo {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Namespace(Vec<String>);
 
+#[cfg(feature = "graphql")]
+#[async_graphql::Scalar]
+impl async_graphql::ScalarType for Namespace {
+    fn parse(value: async_graphql::Value) -> async_graphql::InputValueResult<Self> {
+        match value {
+            async_graphql::Value::String(namespace) => Ok(Namespace::from(namespace)),
+            _ => Err(async_graphql::InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> async_graphql::Value {
+        async_graphql::Value::String(self.0.join("."))
+    }
+}
+
+impl std::fmt::Display for Namespace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
 impl From<NamespaceBridge> for Namespace {
     fn from(value: NamespaceBridge) -> Self {
         Namespace::from(value.to_string())
@@ -270,6 +300,7 @@ impl From<RequestDataBridge> for RequestData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct GovsData {
     pub governance_id: String,
     pub active: bool,
@@ -289,6 +320,7 @@ impl From<GovsDataBridge> for GovsData {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct RegisterDataSubj {
     pub subject_id: String,
     pub schema: String,
@@ -311,9 +343,9 @@ impl From<RegisterDataSubjBridge> for RegisterDataSubj {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RequestInfo {
-    status: String,
-    version: u64,
-    error: Option<String>,
+    pub status: String,
+    pub version: u64,
+    pub error: Option<String>,
 }
 
 impl From<RequestInfoBridge> for RequestInfo {
@@ -393,8 +425,8 @@ impl From<SignedInfoBridge<FactInfoBridge>> for SignedInfo<FactInfo> {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FactInfo {
-    payload: Value,
-    subject_id: String,
+    pub payload: Value,
+    pub subject_id: String,
 }
 
 impl From<FactInfoBridge> for FactInfo {
@@ -407,6 +439,7 @@ impl From<FactInfoBridge> for FactInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct SignatureInfo {
     pub signer: String,
     pub timestamp: u64,
@@ -426,6 +459,7 @@ impl From<SignatureInfoBridge> for SignatureInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct SubjectInfo {
     pub subject_id: String,
     pub governance_id: String,
@@ -463,6 +497,7 @@ impl From<SubjectInfoBridge> for SubjectInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct SignaturesInfo {
     pub subject_id: String,
     pub sn: u64,
@@ -502,6 +537,7 @@ impl From<SignaturesInfoBridge> for SignaturesInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Union))]
 pub enum ProtocolsSignaturesInfo {
     Signature(SignatureInfo),
     TimeOut(TimeOutResponseInfo),
@@ -521,6 +557,7 @@ impl From<ProtocolsSignaturesInfoBridge> for ProtocolsSignaturesInfo {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct TimeOutResponseInfo {
     pub who: String,
     pub re_trys: u32,
@@ -691,3 +728,382 @@ impl From<RoutingNodeBridge> for RoutingNode {
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Governance {
+    /// The version of the governance.
+    pub version: u64,
+    /// The members of the governance.
+    pub members: Vec<Member>,
+    /// The roles assigned to the members.
+    pub roles: Vec<Role>,
+    /// The schemas defined by the governance.
+    pub schemas: Vec<Schema>,
+    /// The quorum policies of each schema.
+    pub policies: Vec<Policy>,
+}
+
+impl TryFrom<Value> for Governance {
+    type Error = serde_json::Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct Member {
+    /// The controller id of the member.
+    pub id: String,
+    /// The name of the member, unique within the governance.
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Role {
+    /// Who holds the role.
+    pub who: Who,
+    /// The namespace in which the role applies, empty for all of them.
+    pub namespace: String,
+    /// The role itself.
+    pub role: RoleType,
+    /// The schemas to which the role applies.
+    pub schema: SchemaEnum,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoleType {
+    Approver,
+    Evaluator,
+    Validator,
+    Witness,
+    Creator,
+    Issuer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Who {
+    /// A member given its controller id.
+    Id(String),
+    /// A member given its name.
+    Name(String),
+    /// Every member of the governance.
+    Members,
+    /// Anyone, member or not.
+    All,
+    /// Anyone who is not a member of the governance.
+    NotMembers,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SchemaEnum {
+    /// A single schema given its id.
+    Id(String),
+    /// Every schema except the governance one.
+    NotGovernance,
+    /// Every schema.
+    All,
+}
+
+impl SchemaEnum {
+    pub fn applies_to(&self, schema_id: &str) -> bool {
+        match self {
+            SchemaEnum::Id(id) => id == schema_id,
+            SchemaEnum::NotGovernance => schema_id != "governance",
+            SchemaEnum::All => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Schema {
+    /// The identifier of the schema.
+    pub id: String,
+    /// The JSON Schema that the state of the subjects must satisfy, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    /// The initial state of the subjects of this schema.
+    pub initial_value: Value,
+    /// The contract that evaluates the facts of the subjects.
+    pub contract: Contract,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Contract {
+    /// The source code of the contract encoded in base64.
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Policy {
+    /// The identifier of the schema the policy applies to.
+    pub id: String,
+    /// The quorum required in the evaluation phase.
+    pub evaluate: Validation,
+    /// The quorum required in the approval phase.
+    pub approve: Validation,
+    /// The quorum required in the validation phase.
+    pub validate: Validation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Validation {
+    pub quorum: Quorum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Quorum {
+    /// More than half of the signers.
+    Majority,
+    /// A fixed number of signers.
+    Fixed(u32),
+    /// A percentage of the signers, between 0 and 1.
+    Percentage(f64),
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+pub enum EventType {
+    Create,
+    Fact,
+    Transfer,
+    Confirm,
+    Reject,
+    EOL,
+}
+
+impl From<&EventRequestInfo> for EventType {
+    fn from(value: &EventRequestInfo) -> Self {
+        match value {
+            EventRequestInfo::Create(..) => Self::Create,
+            EventRequestInfo::Fact(..) => Self::Fact,
+            EventRequestInfo::Transfer(..) => Self::Transfer,
+            EventRequestInfo::Confirm(..) => Self::Confirm,
+            EventRequestInfo::Reject(..) => Self::Reject,
+            EventRequestInfo::EOL(..) => Self::EOL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
+pub struct CursorEvents {
+    /// The events of the page, in ascending order of sn.
+    pub events: Vec<EventInfo>,
+    /// Cursor to the next page, if any.
+    pub next: Option<String>,
+    /// Cursor to the previous page, if any.
+    pub prev: Option<String>,
+}
+
+/// A page of events, numbered or keyed by sn.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum EventsPage {
+    Numbered(PaginatorEvents),
+    Cursor(CursorEvents),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortField {
+    Name,
+    Schema,
+    Sn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubjectState {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The identifier of the governance of the subject.
+    pub governance_id: String,
+    /// The schema of the subject.
+    pub schema_id: String,
+    /// The sequence number at which the state was rebuilt.
+    pub sn: u64,
+    /// The state of the subject at that sequence number.
+    pub properties: Value,
+    /// The Blake3 hash of the canonical JSON serialization of the properties. It is not the
+    /// state hash of the node.
+    pub properties_hash: String,
+}
+
+/// The current state of a subject, or its state rebuilt at a given sn.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum StateResponse {
+    Current(SubjectInfo),
+    Past(SubjectState),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StateDiff {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The sequence number of the initial state.
+    pub from: u64,
+    /// The sequence number of the final state.
+    pub to: u64,
+    /// The RFC 6902 JSON Patch that turns the initial state into the final one.
+    pub patch: Value,
+    /// The changes of each field.
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+    /// JSON pointer to the field.
+    pub path: String,
+    /// The value of the field in the initial state, if it existed.
+    pub from: Option<Value>,
+    /// The value of the field in the final state, if it exists.
+    pub to: Option<Value>,
+    /// The sequence numbers of the events that touched the field.
+    pub events: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BundleManifest {
+    /// The identifier of the exported subject.
+    pub subject_id: String,
+    /// The identifier of the governance of the subject.
+    pub governance_id: String,
+    /// The version of the governance when the bundle was exported.
+    pub governance_version: u64,
+    /// The last sequence number included in the bundle.
+    pub sn: u64,
+    /// The hash of the properties rebuilt from the events of the bundle.
+    pub properties_hash: String,
+    /// The files of the bundle with their hashes.
+    pub files: Vec<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BundleFile {
+    /// The path of the file within the bundle.
+    pub name: String,
+    /// The Blake3 hash of the content of the file, encoded in base64url.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BundleReport {
+    /// The identifier of the subject of the bundle, if the manifest could be read.
+    pub subject_id: Option<String>,
+    /// The last sequence number of the bundle, if the manifest could be read.
+    pub sn: Option<u64>,
+    /// The consistency checks that failed. No errors does not prove that the bundle is authentic.
+    pub errors: Vec<BundleError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BundleError {
+    /// The sequence number of the event that failed, if the error concerns one.
+    pub sn: Option<u64>,
+    /// The file of the bundle that failed, if the error concerns one.
+    pub file: Option<String>,
+    /// Why the check failed.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignaturesQuorum {
+    /// The identifier of the subject.
+    pub subject_id: String,
+    /// The sequence number of the event.
+    pub sn: u64,
+    /// The version of the governance in force when the event was signed.
+    pub governance_version: u64,
+    /// The analysis of the evaluation signatures, if the event was evaluated.
+    pub evaluation: Option<ProtocolQuorum>,
+    /// The analysis of the approval signatures, if the event was approved.
+    pub approval: Option<ProtocolQuorum>,
+    /// The analysis of the validation signatures.
+    pub validation: ProtocolQuorum,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProtocolQuorum {
+    /// The quorum required by the policy of the schema.
+    pub quorum: Quorum,
+    /// Whether the role is open to any controller or to those that are not members, so that
+    /// `eligible` only lists the members that hold it by name or id.
+    pub open: bool,
+    /// The controller ids of the members that hold the role, if they can be enumerated.
+    pub eligible: Vec<String>,
+    /// The number of eligible signatures required to reach the quorum.
+    pub required: u64,
+    /// Whether the quorum was reached.
+    pub reached: bool,
+    /// The signers of the event.
+    pub signers: Vec<Signer>,
+    /// The members that did not answer in time.
+    pub timed_out: Vec<TimedOutSigner>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Signer {
+    /// The controller id of the signer.
+    pub signer: String,
+    /// The name of the signer in the governance, if it is a member.
+    pub name: Option<String>,
+    /// Whether the signer holds the role required by the protocol.
+    pub eligible: bool,
+    /// The timestamp of the signature.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TimedOutSigner {
+    /// The controller id of the member.
+    pub who: String,
+    /// The name of the member in the governance, if any.
+    pub name: Option<String>,
+    /// The number of retries before giving up.
+    pub re_trys: u32,
+    /// The timestamp of the timeout.
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CacheMetrics {
+    /// Whether responses are cached, which requires a memory limit.
+    pub enabled: bool,
+    /// The number of cached responses.
+    pub entries: u64,
+    /// The approximate memory used by the cached responses, in bytes.
+    pub memory: u64,
+    /// The memory over which the least recently used responses are evicted, in bytes.
+    pub max_memory: u64,
+    /// The metrics of each cached route.
+    pub routes: Vec<RouteCacheMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RouteCacheMetrics {
+    /// The name of the route: governances, subjects or state.
+    pub route: String,
+    /// The time to live of the responses of the route, in milliseconds.
+    pub ttl: u64,
+    /// The number of requests answered from the cache.
+    pub hits: u64,
+    /// The number of requests that had to ask the node.
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One JSON object per line.
+    #[default]
+    Ndjson,
+    /// One row per event, with the request flattened into columns.
+    Csv,
+}
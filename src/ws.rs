@@ -0,0 +1,992 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use axum::{
+    Extension, Router,
+    body::{Body, to_bytes},
+    extract::{
+        WebSocketUpgrade,
+        ws::{CloseFrame, Message, Utf8Bytes, WebSocket, close_code},
+    },
+    http::{HeaderMap, Method, Request, header},
+    response::Response,
+    routing::get,
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use kore_bridge::Bridge;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::{
+    sync::mpsc,
+    task::{JoinHandle, JoinSet},
+};
+use tower::ServiceExt;
+
+use crate::{
+    middleware::StreamPermit,
+    pagination::EVENTS_BATCH,
+    server::{API_PREFIX, POLL_INTERVAL, get_events_batch},
+    shutdown::Shutdown,
+    wrappers::{ApproveInfo, RequestInfo},
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Maximum size of a response of the API forwarded over the socket.
+const MAX_RESPONSE: usize = 64 * 1024 * 1024;
+/// Maximum number of responses and notifications waiting to be written to a socket.
+const OUTGOING_BUFFER: usize = 64;
+/// Maximum number of calls in progress on a connection.
+const MAX_CALLS: usize = 16;
+/// Maximum number of active subscriptions on a connection.
+const MAX_SUBSCRIPTIONS: usize = 32;
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+/// Notification pushed to the client for an active subscription.
+#[derive(Debug, Serialize)]
+struct RpcNotification {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: SubscriptionResult,
+}
+
+#[derive(Debug, Serialize)]
+struct SubscriptionResult {
+    subscription: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+/// What a client can subscribe to.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Subscription {
+    /// New events of a subject, after the given sn or after the current one.
+    Events {
+        subject_id: String,
+        after_sn: Option<u64>,
+    },
+    /// Changes in the state of an event request.
+    Request { request_id: String },
+    /// Approval requests received for a subject.
+    Approval { subject_id: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct Unsubscribe {
+    subscription: u64,
+}
+
+/// How the `body` parameter of a method is sent to the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Payload {
+    None,
+    Json,
+    /// A base64 string sent as raw bytes.
+    Binary,
+}
+
+/// The JSON-RPC methods and the route of the API that serves each of them, one for every
+/// operation of the OpenAPI document.
+const ROUTES: &[(&str, Method, &str, Payload)] = &[
+    (
+        "send_event_request",
+        Method::POST,
+        "/event-request",
+        Payload::Json,
+    ),
+    (
+        "get_request_state",
+        Method::GET,
+        "/event-request/{request_id}",
+        Payload::None,
+    ),
+    (
+        "get_approval",
+        Method::GET,
+        "/approval-request/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "patch_approval",
+        Method::PATCH,
+        "/approval-request/{subject_id}",
+        Payload::Json,
+    ),
+    ("put_auth", Method::PUT, "/auth/{subject_id}", Payload::Json),
+    ("get_all_auth_subjects", Method::GET, "/auth", Payload::None),
+    (
+        "get_witnesses_subject",
+        Method::GET,
+        "/auth/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "delete_auth_subject",
+        Method::DELETE,
+        "/auth/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "update_subject",
+        Method::POST,
+        "/update/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "check_transfer",
+        Method::POST,
+        "/check-transfer/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "manual_distribution",
+        Method::POST,
+        "/manual-distribution/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_all_govs",
+        Method::GET,
+        "/register-governances",
+        Payload::None,
+    ),
+    (
+        "get_all_subjects",
+        Method::GET,
+        "/register-subjects/{governance_id}",
+        Payload::None,
+    ),
+    (
+        "get_governance_members",
+        Method::GET,
+        "/governances/{governance_id}/members",
+        Payload::None,
+    ),
+    (
+        "get_governance_roles",
+        Method::GET,
+        "/governances/{governance_id}/roles",
+        Payload::None,
+    ),
+    (
+        "get_governance_schemas",
+        Method::GET,
+        "/governances/{governance_id}/schemas",
+        Payload::None,
+    ),
+    (
+        "get_governance_policies",
+        Method::GET,
+        "/governances/{governance_id}/policies",
+        Payload::None,
+    ),
+    (
+        "get_events",
+        Method::GET,
+        "/events/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_event_sn",
+        Method::GET,
+        "/event/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_first_or_end_events",
+        Method::GET,
+        "/events-first-last/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_state",
+        Method::GET,
+        "/state/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_state_diff",
+        Method::GET,
+        "/state/{subject_id}/diff",
+        Payload::None,
+    ),
+    (
+        "get_signatures",
+        Method::GET,
+        "/signatures/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_signatures_quorum",
+        Method::GET,
+        "/signatures/{subject_id}/quorum",
+        Payload::None,
+    ),
+    (
+        "export_subject",
+        Method::GET,
+        "/subjects/{subject_id}/export",
+        Payload::None,
+    ),
+    (
+        "verify_bundle",
+        Method::POST,
+        "/verify/bundle",
+        Payload::Binary,
+    ),
+    (
+        "get_controller_id",
+        Method::GET,
+        "/controller-id",
+        Payload::None,
+    ),
+    ("get_peer_id", Method::GET, "/peer-id", Payload::None),
+    ("get_config", Method::GET, "/config", Payload::None),
+    ("get_keys", Method::GET, "/keys", Payload::None),
+    (
+        "get_pending_transfers",
+        Method::GET,
+        "/pending-transfers",
+        Payload::None,
+    ),
+    (
+        "get_subject",
+        Method::GET,
+        "/subjects/{subject_id}",
+        Payload::None,
+    ),
+    (
+        "get_subject_diff",
+        Method::GET,
+        "/subjects/{subject_id}/diff",
+        Payload::None,
+    ),
+    (
+        "get_subject_events",
+        Method::GET,
+        "/subjects/{subject_id}/events",
+        Payload::None,
+    ),
+    (
+        "get_subject_event",
+        Method::GET,
+        "/subjects/{subject_id}/events/{sn}",
+        Payload::None,
+    ),
+    (
+        "get_subject_signatures",
+        Method::GET,
+        "/subjects/{subject_id}/signatures",
+        Payload::None,
+    ),
+    (
+        "get_subject_signatures_quorum",
+        Method::GET,
+        "/subjects/{subject_id}/signatures/quorum",
+        Payload::None,
+    ),
+    (
+        "get_subject_witnesses",
+        Method::GET,
+        "/subjects/{subject_id}/witnesses",
+        Payload::None,
+    ),
+    (
+        "put_subject_witnesses",
+        Method::PUT,
+        "/subjects/{subject_id}/witnesses",
+        Payload::Json,
+    ),
+    (
+        "delete_subject_witnesses",
+        Method::DELETE,
+        "/subjects/{subject_id}/witnesses",
+        Payload::None,
+    ),
+    (
+        "get_subject_approval",
+        Method::GET,
+        "/subjects/{subject_id}/approval",
+        Payload::None,
+    ),
+    (
+        "patch_subject_approval",
+        Method::PATCH,
+        "/subjects/{subject_id}/approval",
+        Payload::Json,
+    ),
+    (
+        "post_subject_update",
+        Method::POST,
+        "/subjects/{subject_id}/updates",
+        Payload::None,
+    ),
+    (
+        "post_subject_distribution",
+        Method::POST,
+        "/subjects/{subject_id}/distributions",
+        Payload::None,
+    ),
+    (
+        "post_subject_transfer_check",
+        Method::POST,
+        "/subjects/{subject_id}/transfer-checks",
+        Payload::None,
+    ),
+    (
+        "get_governances",
+        Method::GET,
+        "/governances",
+        Payload::None,
+    ),
+    (
+        "get_governance_subjects",
+        Method::GET,
+        "/governances/{governance_id}/subjects",
+        Payload::None,
+    ),
+    ("post_request", Method::POST, "/requests", Payload::Json),
+    (
+        "get_request",
+        Method::GET,
+        "/requests/{request_id}",
+        Payload::None,
+    ),
+    ("get_transfers", Method::GET, "/transfers", Payload::None),
+    (
+        "export_subject_events",
+        Method::GET,
+        "/events/{subject_id}/export",
+        Payload::None,
+    ),
+    (
+        "export_governance_events",
+        Method::GET,
+        "/governances/{governance_id}/events/export",
+        Payload::None,
+    ),
+    (
+        "get_cache_metrics",
+        Method::GET,
+        "/cache-metrics",
+        Payload::None,
+    ),
+    ("get_readiness", Method::GET, "/health/ready", Payload::None),
+];
+
+/// Maps a JSON-RPC method to the route of the API that serves it.
+fn route(method: &str) -> Option<(Method, &'static str, Payload)> {
+    ROUTES
+        .iter()
+        .find(|(name, ..)| *name == method)
+        .map(|(_, method, path, payload)| (method.clone(), *path, *payload))
+}
+
+#[derive(Clone)]
+struct WsContext {
+    bridge: Arc<Bridge>,
+    api: Arc<OnceLock<Router>>,
+    shutdown: Shutdown,
+}
+
+/// Builds the `/ws` route, which serves the operations of the API over a JSON-RPC 2.0
+/// WebSocket connection.
+///
+/// Calls are forwarded to the versioned routes of `api`, the complete service once it is
+/// built, with the headers of the upgrade request. So they go through the same
+/// authentication, timeouts, concurrency limit and body limits as plain HTTP requests.
+pub fn build_ws_routes(
+    bridge: Arc<Bridge>,
+    api: Arc<OnceLock<Router>>,
+    shutdown: Shutdown,
+) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .layer(Extension(WsContext {
+            bridge,
+            api,
+            shutdown,
+        }))
+}
+
+async fn ws_handler(
+    Extension(context): Extension<WsContext>,
+    permit: Option<Extension<StreamPermit>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let headers: HeaderMap = headers
+        .iter()
+        .filter(|(name, _)| {
+            !name.as_str().starts_with("sec-websocket")
+                && ![
+                    header::CONNECTION,
+                    header::UPGRADE,
+                    header::CONTENT_LENGTH,
+                    header::CONTENT_TYPE,
+                    header::ACCEPT,
+                    header::ACCEPT_ENCODING,
+                ]
+                .contains(*name)
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    ws.on_upgrade(move |socket| async move {
+        // The stream permit, if the route is limited, is held for as long as the socket is open.
+        let _permit = permit;
+        connection(socket, context, Arc::new(headers)).await
+    })
+}
+
+async fn connection(mut socket: WebSocket, context: WsContext, headers: Arc<HeaderMap>) {
+    let shutdown = context.shutdown.clone();
+    let stopping = shutdown.started();
+    tokio::pin!(stopping);
+
+    let (sender, mut receiver) = mpsc::channel::<String>(OUTGOING_BUFFER);
+    let mut session = Session {
+        context,
+        headers,
+        sender,
+        subscriptions: HashMap::new(),
+        next_subscription: 0,
+        calls: JoinSet::new(),
+    };
+
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+
+                let outgoing = match parse(text.as_str()) {
+                    Ok(request) => session.request(request),
+                    Err(error) => Some(response(Value::Null, Err(error))),
+                };
+                let Some(outgoing) = outgoing else {
+                    continue;
+                };
+                outgoing
+            }
+            Some(outgoing) = receiver.recv() => outgoing,
+            Some(_) = session.calls.join_next(), if !session.calls.is_empty() => continue,
+            _ = &mut stopping => {
+                let close = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: Utf8Bytes::from_static("The server is shutting down"),
+                };
+                let _ = socket.send(Message::Close(Some(close))).await;
+                break;
+            }
+        };
+
+        if socket.send(Message::Text(outgoing.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The subscriptions and calls in progress of a connection, which are aborted when it closes.
+struct Session {
+    context: WsContext,
+    headers: Arc<HeaderMap>,
+    sender: mpsc::Sender<String>,
+    subscriptions: HashMap<u64, JoinHandle<()>>,
+    next_subscription: u64,
+    calls: JoinSet<()>,
+}
+
+impl Session {
+    /// Handles a request, returning its response unless it is sent later or not at all.
+    fn request(&mut self, request: RpcRequest) -> Option<String> {
+        let id = request.id.clone();
+
+        let result = match request.method.as_str() {
+            "subscribe" => self.subscribe(request.params),
+            "unsubscribe" => match serde_json::from_value::<Unsubscribe>(request.params) {
+                Ok(Unsubscribe { subscription }) => {
+                    let task = self.subscriptions.remove(&subscription);
+                    if let Some(task) = &task {
+                        task.abort();
+                    }
+                    Ok(json!(task.is_some()))
+                }
+                Err(e) => Err(RpcError::new(INVALID_PARAMS, e.to_string())),
+            },
+            _ if self.calls.len() >= MAX_CALLS => Err(RpcError::new(
+                SERVER_ERROR,
+                format!("At most {} calls in progress per connection", MAX_CALLS),
+            )),
+            _ => {
+                let api = self.context.api.clone();
+                let headers = self.headers.clone();
+                let sender = self.sender.clone();
+                self.calls.spawn(async move {
+                    let result = call(&api, &headers, &request.method, request.params).await;
+                    if let Some(id) = id {
+                        let _ = sender.send(response(id, result)).await;
+                    }
+                });
+                return None;
+            }
+        };
+
+        id.map(|id| response(id, result))
+    }
+
+    fn subscribe(&mut self, params: Value) -> Result<Value, RpcError> {
+        let subscription = serde_json::from_value::<Subscription>(params)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+
+        self.subscriptions.retain(|_, task| !task.is_finished());
+        if self.subscriptions.len() >= MAX_SUBSCRIPTIONS {
+            return Err(RpcError::new(
+                SERVER_ERROR,
+                format!("At most {} subscriptions per connection", MAX_SUBSCRIPTIONS),
+            ));
+        }
+
+        self.next_subscription += 1;
+        let notifier = Notifier {
+            subscription: self.next_subscription,
+            sender: self.sender.clone(),
+        };
+        self.subscriptions.insert(
+            self.next_subscription,
+            tokio::spawn(watch(self.context.bridge.clone(), subscription, notifier)),
+        );
+        Ok(json!(self.next_subscription))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        for task in self.subscriptions.values() {
+            task.abort();
+        }
+    }
+}
+
+fn parse(text: &str) -> Result<RpcRequest, RpcError> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| RpcError::new(PARSE_ERROR, e.to_string()))?;
+    let request: RpcRequest =
+        serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_REQUEST, e.to_string()))?;
+
+    if request.jsonrpc != "2.0" {
+        return Err(RpcError::new(
+            INVALID_REQUEST,
+            "Only JSON-RPC 2.0 is supported",
+        ));
+    }
+    Ok(request)
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+
+    serde_json::to_string(&RpcResponse {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    })
+    .unwrap_or_default()
+}
+
+/// Replaces the parameters of a path with the params of the same name, which must be strings,
+/// numbers or booleans, percent-encoded so that they stay within their segment.
+fn fill_path(path: &str, params: &mut serde_json::Map<String, Value>) -> Result<String, RpcError> {
+    let mut uri = path.to_owned();
+    while let Some(start) = uri.find('{') {
+        let end = uri[start..].find('}').map_or(uri.len(), |x| start + x);
+        let name = uri[start + 1..end].to_owned();
+        let value = match params.remove(&name) {
+            Some(Value::String(value)) => value,
+            Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+            _ => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("Missing string, number or boolean param {}", name),
+                ));
+            }
+        };
+        let value = utf8_percent_encode(&value, NON_ALPHANUMERIC).to_string();
+        uri.replace_range(start..=end, &value);
+    }
+    Ok(uri)
+}
+
+/// Serves a method by forwarding it to the route of the API.
+///
+/// Path parameters are taken from the params by name, `body` is sent as the request
+/// body and every other param goes in the query string.
+async fn call(
+    api: &OnceLock<Router>,
+    headers: &HeaderMap,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    let Some((http_method, path, payload)) = route(method) else {
+        return Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Unknown method {}", method),
+        ));
+    };
+
+    let mut params = match params {
+        Value::Object(params) => params,
+        Value::Null => serde_json::Map::new(),
+        _ => {
+            return Err(RpcError::new(INVALID_PARAMS, "Params must be an object"));
+        }
+    };
+
+    let mut uri = format!("{}{}", API_PREFIX, fill_path(path, &mut params)?);
+
+    let body = params.remove("body");
+    let query: Vec<(String, String)> = params
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => (key, value),
+            value => (key, value.to_string()),
+        })
+        .collect();
+    if !query.is_empty() {
+        let query = serde_urlencoded::to_string(query)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+        uri = format!("{}?{}", uri, query);
+    }
+
+    let mut request = Request::builder().method(http_method).uri(uri);
+    if let Some(request_headers) = request.headers_mut() {
+        request_headers.extend(headers.clone());
+    }
+
+    let body = match (payload, body) {
+        (Payload::Json, Some(body)) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        (Payload::Binary, Some(Value::String(body))) => match STANDARD.decode(body) {
+            Ok(bytes) => Body::from(bytes),
+            Err(e) => {
+                return Err(RpcError::new(
+                    INVALID_PARAMS,
+                    format!("The body is not valid base64: {}", e),
+                ));
+            }
+        },
+        (Payload::None, None) => Body::empty(),
+        (Payload::None, Some(_)) => {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Method {} takes no body", method),
+            ));
+        }
+        (_, _) => {
+            return Err(RpcError::new(INVALID_PARAMS, "Missing or invalid body"));
+        }
+    };
+
+    let request = request
+        .body(body)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+    let Some(api) = api.get().cloned() else {
+        return Err(RpcError::new(SERVER_ERROR, "The API is not ready"));
+    };
+    let Ok(response) = api.oneshot(request).await;
+
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("application/json"));
+    let bytes = to_bytes(response.into_body(), MAX_RESPONSE)
+        .await
+        .map_err(|e| RpcError::new(SERVER_ERROR, e.to_string()))?;
+
+    let value = if bytes.is_empty() {
+        Value::Null
+    } else if is_json {
+        serde_json::from_slice(&bytes).map_err(|e| {
+            RpcError::new(
+                SERVER_ERROR,
+                format!("The API answered with invalid JSON: {}", e),
+            )
+        })?
+    } else {
+        Value::String(STANDARD.encode(&bytes))
+    };
+
+    if status.is_success() {
+        return Ok(value);
+    }
+
+    let code = if status.is_client_error() {
+        INVALID_PARAMS
+    } else {
+        SERVER_ERROR
+    };
+    let message = match &value {
+        Value::String(message) if is_json => message.clone(),
+        _ => status.canonical_reason().unwrap_or("Error").to_owned(),
+    };
+    Err(RpcError {
+        code,
+        message,
+        data: Some(json!({ "status": status.as_u16(), "body": value })),
+    })
+}
+
+struct Notifier {
+    subscription: u64,
+    sender: mpsc::Sender<String>,
+}
+
+impl Notifier {
+    /// Pushes a notification, returning false once the connection is closed.
+    async fn send(&self, result: Result<Value, RpcError>) -> bool {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+
+        let notification = RpcNotification {
+            jsonrpc: "2.0",
+            method: "subscription",
+            params: SubscriptionResult {
+                subscription: self.subscription,
+                result,
+                error,
+            },
+        };
+        match serde_json::to_string(&notification) {
+            Ok(notification) => self.sender.send(notification).await.is_ok(),
+            Err(_) => true,
+        }
+    }
+}
+
+/// Polls the node for a subscription until it fails or the client goes away.
+async fn watch(bridge: Arc<Bridge>, subscription: Subscription, notifier: Notifier) {
+    let kore = |e: String| RpcError::new(SERVER_ERROR, e);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    match subscription {
+        Subscription::Events {
+            subject_id,
+            after_sn,
+        } => {
+            let mut last_sn = after_sn;
+            loop {
+                interval.tick().await;
+
+                let sn = match bridge.get_subject(subject_id.clone()).await {
+                    Ok(subject) => subject.sn,
+                    Err(e) => {
+                        notifier.send(Err(kore(e.to_string()))).await;
+                        return;
+                    }
+                };
+
+                let Some(from) = last_sn.map(|x| x + 1) else {
+                    last_sn = Some(sn);
+                    continue;
+                };
+
+                for batch in from / EVENTS_BATCH..=sn / EVENTS_BATCH {
+                    let events = match get_events_batch(&bridge, &subject_id, batch).await {
+                        Ok(events) => events,
+                        Err(e) => {
+                            notifier.send(Err(kore(e.to_string()))).await;
+                            return;
+                        }
+                    };
+
+                    for event in events.into_iter().filter(|x| (from..=sn).contains(&x.sn)) {
+                        last_sn = Some(event.sn);
+                        let event = serde_json::to_value(event).map_err(|e| kore(e.to_string()));
+                        if !notifier.send(event).await {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        Subscription::Request { request_id } => {
+            let mut last = None;
+            loop {
+                interval.tick().await;
+
+                let state = match bridge.get_request_state(request_id.clone()).await {
+                    Ok(state) => serde_json::to_value(RequestInfo::from(state))
+                        .map_err(|e| kore(e.to_string())),
+                    Err(e) => {
+                        notifier.send(Err(kore(e.to_string()))).await;
+                        return;
+                    }
+                };
+
+                let Ok(state) = state else {
+                    notifier.send(state).await;
+                    return;
+                };
+                if last.as_ref() != Some(&state) {
+                    last = Some(state.clone());
+                    if !notifier.send(Ok(state)).await {
+                        return;
+                    }
+                }
+            }
+        }
+        Subscription::Approval { subject_id } => {
+            let mut last = None;
+            loop {
+                interval.tick().await;
+
+                // The node answers with an error while there is no approval request.
+                let Ok(approval) = bridge.get_approval(subject_id.clone()).await else {
+                    last = None;
+                    continue;
+                };
+                let Ok(approval) = serde_json::to_value(ApproveInfo::from(approval)) else {
+                    continue;
+                };
+
+                if last.as_ref() != Some(&approval) {
+                    last = Some(approval.clone());
+                    if !notifier.send(Ok(approval)).await {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use axum::http::Method;
+    use utoipa::OpenApi;
+
+    use serde_json::{Map, Value, json};
+
+    use super::{INVALID_PARAMS, ROUTES, fill_path};
+    use crate::doc::ApiDoc;
+
+    #[test]
+    fn every_documented_operation_has_a_method() {
+        let documented: BTreeSet<(String, String)> = ApiDoc::openapi()
+            .paths
+            .paths
+            .into_iter()
+            .flat_map(|(path, item)| {
+                [
+                    (Method::GET, item.get),
+                    (Method::PUT, item.put),
+                    (Method::POST, item.post),
+                    (Method::DELETE, item.delete),
+                    (Method::PATCH, item.patch),
+                ]
+                .into_iter()
+                .filter(|(_, operation)| operation.is_some())
+                .map(move |(method, _)| (method.to_string(), path.clone()))
+            })
+            .collect();
+
+        let routes: BTreeSet<(String, String)> = ROUTES
+            .iter()
+            .map(|(_, method, path, _)| (method.to_string(), path.to_string()))
+            .collect();
+
+        assert_eq!(routes, documented);
+        assert_eq!(routes.len(), ROUTES.len());
+    }
+
+    #[test]
+    fn method_names_are_unique() {
+        let names: BTreeSet<&str> = ROUTES.iter().map(|(name, ..)| *name).collect();
+        assert_eq!(names.len(), ROUTES.len());
+    }
+
+    fn object(value: Value) -> Map<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn path_params_are_percent_encoded() {
+        let mut params = object(json!({ "subject_id": "a/b?c d", "sn": 1 }));
+        let path = fill_path("/event/{subject_id}", &mut params).unwrap();
+        assert_eq!(path, "/event/a%2Fb%3Fc%20d");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn path_params_accept_numbers_and_booleans() {
+        let mut params = object(json!({ "id": 7, "active": true }));
+        let path = fill_path("/{id}/{active}", &mut params).unwrap();
+        assert_eq!(path, "/7/true");
+        assert!(params.is_empty());
+
+        let mut params = object(json!({ "id": [1] }));
+        let error = fill_path("/{id}", &mut params).unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+}